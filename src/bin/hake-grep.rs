@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 use std::fs::{self, File};
 use std::io::{self, prelude::*, BufReader, Write};
 
@@ -24,14 +24,13 @@ struct Options {
 }
 
 impl Options {
-    pub fn map(&self) -> Map {
+    pub fn map(&self) -> Result<Map, String> {
         let mut map = Map::new();
         for arg in self.map.clone() {
-            if let Ok((key, val)) = Map::parse_pair(&arg) {
-                map.add(key, val);
-            }
+            let (key, val) = Map::parse_pair(&arg)?;
+            map.add(key, val);
         }
-        map
+        Ok(map)
     }
 }
 
@@ -45,9 +44,17 @@ struct LogLine {
 enum LogEntity {
     Make(MakeArgs),
     Metric(Metric),
+    Status(StatusLine),
+    /// a line from stderr of make
+    Stderr(String),
     Stuff,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct StatusLine {
+    status: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct MakeArgs {
     name: String,
@@ -59,7 +66,7 @@ impl MakeArgs {
     pub fn map(&self) -> Map {
         let mut map = Map::new();
         for arg in self.make_args.iter() {
-            if let Ok((key, val)) = Map::parse_pair(&arg) {
+            if let Some((key, val)) = Map::parse_literal(arg) {
                 map.add(key, val);
             }
         }
@@ -68,7 +75,7 @@ impl MakeArgs {
     pub fn json(&self) -> BTreeMap<String, serde_json::Value> {
         let mut map = BTreeMap::new();
         for arg in self.make_args.iter() {
-            if let Ok((key, val)) = Map::parse_pair(&arg) {
+            if let Some((key, val)) = Map::parse_literal(arg) {
                 match val {
                     Value::Val(x) => {
                         if let Ok(x) = x.parse::<i64>() {
@@ -95,16 +102,23 @@ impl MakeArgs {
 
 impl LogEntity {
     fn parse(line: &str) -> Self {
-        if let Ok(make) = serde_json::from_str::<MakeArgs>(line) {
+        if let Some(err) = line.strip_prefix("[stderr] ") {
+            Self::Stderr(err.to_string())
+        } else if let Ok(make) = serde_json::from_str::<MakeArgs>(line) {
             Self::Make(make)
         } else if let Ok(metric) = serde_json::from_str::<Metric>(line) {
             Self::Metric(metric)
+        } else if let Ok(status) = serde_json::from_str::<StatusLine>(line) {
+            Self::Status(status)
         } else {
             Self::Stuff
         }
     }
 }
 
+/// The last lines of stderr in the output
+const STDERR_TAIL: usize = 10;
+
 struct LogParser {
     pattern: Regex,
 }
@@ -139,15 +153,16 @@ fn map_match(pattern: &Map, refmap: &Map) -> bool {
                     return false;
                 }
             }
-            IntRange(begin, end, _) => match refmap.get(key) {
+            IntRange(begin, end, _) | IntLogRange(begin, end, _) => match refmap.get(key) {
+                // a range may be descending (e.g. 1000~~1)
                 Some(Int(x)) => {
-                    if !(begin <= x && x <= end) {
+                    if !(begin.min(end) <= x && x <= begin.max(end)) {
                         return false;
                     }
                 }
                 Some(Val(x)) => {
                     if let Ok(x) = x.parse::<i64>() {
-                        if !(begin <= &x && &x <= end) {
+                        if !(*begin.min(end) <= x && x <= *begin.max(end)) {
                             return false;
                         }
                     } else {
@@ -156,15 +171,15 @@ fn map_match(pattern: &Map, refmap: &Map) -> bool {
                 }
                 _ => return false,
             },
-            FloatRange(begin, end, _) => match refmap.get(key) {
+            FloatRange(begin, end, _) | FloatLogRange(begin, end, _) => match refmap.get(key) {
                 Some(Float(x)) => {
-                    if !(begin <= x && x <= end) {
+                    if !(begin.min(*end) <= *x && *x <= begin.max(*end)) {
                         return false;
                     }
                 }
                 Some(Val(x)) => {
                     if let Ok(x) = x.parse::<f64>() {
-                        if !(begin <= &x && &x <= end) {
+                        if !(begin.min(*end) <= x && x <= begin.max(*end)) {
                             return false;
                         }
                     } else {
@@ -173,6 +188,13 @@ fn map_match(pattern: &Map, refmap: &Map) -> bool {
                 }
                 _ => return false,
             },
+            Dist(dist) => match refmap.get(key) {
+                Some(Val(x)) => match x.parse::<f64>() {
+                    Ok(x) if dist.clip(x) == x => {}
+                    _ => return false,
+                },
+                _ => return false,
+            },
             Choice(xs) => match refmap.get(key) {
                 Some(Val(z)) => {
                     let mut ok = false;
@@ -192,9 +214,20 @@ fn map_match(pattern: &Map, refmap: &Map) -> bool {
     true
 }
 
+#[allow(
+    clippy::manual_flatten,
+    clippy::partialeq_to_none,
+    clippy::to_string_in_format_args
+)]
 fn main() -> io::Result<()> {
     let opt = Options::from_args();
-    let map = opt.map();
+    let map = match opt.map() {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let log_parser = LogParser::new();
 
@@ -210,6 +243,8 @@ fn main() -> io::Result<()> {
             let mut datetime_begin = None;
             let mut datetime_end = None;
             let mut metrics = BTreeMap::new();
+            let mut status = None;
+            let mut stderr = VecDeque::new();
 
             for line in reader.lines() {
                 if let Ok(line) = line {
@@ -229,6 +264,15 @@ fn main() -> io::Result<()> {
                             LogEntity::Metric(metric) => {
                                 metrics.insert(metric.metric, metric.value);
                             }
+                            LogEntity::Status(line) => {
+                                status = Some(line.status);
+                            }
+                            LogEntity::Stderr(line) => {
+                                if stderr.len() == STDERR_TAIL {
+                                    stderr.pop_front();
+                                }
+                                stderr.push_back(line);
+                            }
                             _ => {}
                         }
                     }
@@ -249,6 +293,8 @@ fn main() -> io::Result<()> {
                     "end": datetime_end,
                 },
                 "metrics": metrics,
+                "status": status,
+                "stderr": stderr,
             });
             let r = writeln!(&mut io::stdout(), "{}", result.to_string());
             if r.is_err() {
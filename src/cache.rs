@@ -0,0 +1,82 @@
+/// Evaluation Cache of Trials
+use std::collections::HashMap;
+
+use crate::map::Param;
+use crate::metric::Metric;
+
+pub enum Lookup {
+    /// evaluated already
+    Hit(Option<Vec<Metric>>),
+    /// the same trial is running; the param waits for it
+    Pending,
+    /// to be evaluated
+    Miss,
+}
+
+/// Results keyed by (makefile, targets, param values, git hash)
+#[derive(Default)]
+pub struct Cache {
+    results: HashMap<Vec<String>, Option<Vec<Metric>>>,
+    /// running trials and the params waiting for them
+    pending: HashMap<Vec<String>, Vec<Param>>,
+}
+
+impl Cache {
+    pub fn new() -> Self {
+        Cache::default()
+    }
+
+    /// Key from the arguments of make (NAME and HID are dropped)
+    pub fn key(make_args: &[String], git_hash: &str) -> Vec<String> {
+        make_args
+            .iter()
+            .filter(|arg| !arg.starts_with("NAME=") && !arg.starts_with("HID="))
+            .cloned()
+            .chain(std::iter::once(git_hash.to_string()))
+            .collect()
+    }
+
+    /// Result from earlier runs (--reuse-results)
+    pub fn insert(&mut self, key: Vec<String>, result: Option<Vec<Metric>>) {
+        self.results.insert(key, result);
+    }
+
+    /// A miss marks the key as running
+    pub fn lookup(&mut self, key: &[String], param: &Param) -> Lookup {
+        if let Some(result) = self.results.get(key) {
+            Lookup::Hit(result.clone())
+        } else if let Some(waiting) = self.pending.get_mut(key) {
+            waiting.push(param.clone());
+            Lookup::Pending
+        } else {
+            self.pending.insert(key.to_vec(), vec![]);
+            Lookup::Miss
+        }
+    }
+
+    /// Record the result of a running trial, and return the params waiting for it
+    pub fn finish(&mut self, key: &[String], result: Option<Vec<Metric>>) -> Vec<Param> {
+        self.results.insert(key.to_vec(), result);
+        self.pending.remove(key).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_lookup() {
+        let mut cache = Cache::new();
+        let args: Vec<String> = ["-f", "Makefile", "train", "NAME=x", "HID=3", "X=1"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let key = Cache::key(&args, "abc");
+        assert_eq!(key, vec!["-f", "Makefile", "train", "X=1", "abc"]);
+        assert!(matches!(cache.lookup(&key, &vec![]), Lookup::Miss));
+        assert!(matches!(cache.lookup(&key, &vec![]), Lookup::Pending));
+        assert_eq!(cache.finish(&key, None).len(), 1);
+        assert!(matches!(cache.lookup(&key, &vec![]), Lookup::Hit(None)));
+    }
+}
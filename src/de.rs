@@ -1,8 +1,17 @@
 /// Differential Evolution
 extern crate rand;
 use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::collections::VecDeque;
 
 use crate::map::{Map, Param, Value};
+use crate::metric::Metric;
+use crate::optimizer::{Best, Optimizer};
+use crate::options::Objective;
+use crate::pareto;
+use crate::qmc::{self, Init};
+use crate::util::sample;
 
 fn clip<T: PartialOrd>(x: T, min: T, max: T) -> T {
     let x = if x < min { min } else { x };
@@ -13,19 +22,48 @@ fn clip<T: PartialOrd>(x: T, min: T, max: T) -> T {
     }
 }
 
-pub fn cross(x: &Param, a: &Param, b: &Param, c: &Param, map: &Map, cr: f64, factor: f64) -> Param {
-    let mut rng = rand::thread_rng();
+/// Cross-over; infeasible children are retried, and rejected for the parent `x` at last
+#[allow(clippy::too_many_arguments)]
+pub fn cross<R: Rng>(
+    x: &Param,
+    a: &Param,
+    b: &Param,
+    c: &Param,
+    map: &Map,
+    cr: f64,
+    factor: f64,
+    rng: &mut R,
+) -> Param {
+    for _ in 0..100 {
+        let z = map.normalize(cross_once(x, a, b, c, map, cr, factor, rng));
+        if map.feasible(&z) {
+            return z;
+        }
+    }
+    x.clone()
+}
 
+#[allow(clippy::too_many_arguments)]
+fn cross_once<R: Rng>(
+    x: &Param,
+    a: &Param,
+    b: &Param,
+    c: &Param,
+    map: &Map,
+    cr: f64,
+    factor: f64,
+    rng: &mut R,
+) -> Param {
     use Value::*;
 
-    let cross_index = Uniform::from(0..map.len()).sample(&mut rng);
+    let cross_index = Uniform::from(0..map.data.len()).sample(rng);
     let cross_prob = Uniform::new(0.0, 1.0);
 
     map.data
         .iter()
         .enumerate()
         .map(|(i, val)| {
-            if i != cross_index && cross_prob.sample(&mut rng) > cr {
+            if i != cross_index && cross_prob.sample(rng) > cr {
                 x[i].clone()
             } else {
                 let key = val.0.clone();
@@ -33,7 +71,7 @@ pub fn cross(x: &Param, a: &Param, b: &Param, c: &Param, map: &Map, cr: f64, fac
                     Val(_) | Int(_) | Float(_) => (key, val.1.clone()),
                     IntRange(begin, end, _) => match (&a[i].1, &b[i].1, &c[i].1) {
                         (Int(a), Int(b), Int(c)) => {
-                            let z = (a.clone() as f64 + (b - c) as f64 * factor).round() as i64;
+                            let z = (*a as f64 + (b - c) as f64 * factor).round() as i64;
                             (key, Int(clip(z, begin, end)))
                         }
                         _ => panic!(),
@@ -45,9 +83,538 @@ pub fn cross(x: &Param, a: &Param, b: &Param, c: &Param, map: &Map, cr: f64, fac
                         }
                         _ => panic!(),
                     },
-                    Choice(_) => a[i].clone(),
+                    IntLogRange(begin, end, _) => match (&a[i].1, &b[i].1, &c[i].1) {
+                        (Int(a), Int(b), Int(c)) => {
+                            let (a, b, c) = ((*a as f64).ln(), (*b as f64).ln(), (*c as f64).ln());
+                            let z = (a + (b - c) * factor).exp().round() as i64;
+                            (key, Int(clip(z, begin.min(end), begin.max(end))))
+                        }
+                        _ => panic!(),
+                    },
+                    FloatLogRange(begin, end, _) => match (&a[i].1, &b[i].1, &c[i].1) {
+                        (Float(a), Float(b), Float(c)) => {
+                            let z = (a.ln() + (b.ln() - c.ln()) * factor).exp();
+                            (key, Float(clip(z, begin.min(end), begin.max(end))))
+                        }
+                        _ => panic!(),
+                    },
+                    Dist(ref dist) => match (&a[i].1, &b[i].1, &c[i].1) {
+                        (Float(a), Float(b), Float(c)) => {
+                            let z = if dist.is_log() {
+                                (a.ln() + (b.ln() - c.ln()) * factor).exp()
+                            } else {
+                                a + (b - c) * factor
+                            };
+                            (key, Float(dist.clip(z)))
+                        }
+                        _ => panic!(),
+                    },
+                    Choice(ref choices) => (
+                        key,
+                        mutate_choice(choices, &a[i].1, &b[i].1, &c[i].1, factor, rng),
+                    ),
                 }
             }
         })
         .collect()
 }
+
+/// Categorical mutation
+///
+/// The difference `b - c` is either 0 or 1 for categorical values,
+/// so the donor `a` jumps to another choice with the probability `factor` only when b != c.
+fn mutate_choice<R: Rng>(
+    choices: &[String],
+    a: &Value,
+    b: &Value,
+    c: &Value,
+    factor: f64,
+    rng: &mut R,
+) -> Value {
+    if b == c || Uniform::new(0.0, 1.0).sample(rng) >= factor {
+        return a.clone();
+    }
+    let others: Vec<&String> = choices
+        .iter()
+        .filter(|&x| a != &Value::Val(x.clone()))
+        .collect();
+    if others.is_empty() {
+        a.clone()
+    } else {
+        let k = Uniform::from(0..others.len()).sample(rng);
+        Value::Val(others[k].clone())
+    }
+}
+
+/// Generation-synchronous DE
+///
+/// A generation is proposed all at once, and the next one is built
+/// only after every trial of the current generation is observed.
+pub struct DifferentialEvolution {
+    map: Map,
+    objectives: Vec<Objective>,
+    np: usize,
+    cr: f64,
+    factor: f64,
+    num_loop: usize,
+    debug: bool,
+    verbose: bool,
+    generation: usize,
+    /// initial seeds from --init
+    seeds: Vec<Param>,
+    pool: Vec<(Param, Vec<Metric>)>,
+    rng: StdRng,
+    queue: VecDeque<Param>,
+    running: usize,
+    restored: usize,
+    best: Best,
+}
+
+impl DifferentialEvolution {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        map: &Map,
+        objectives: &[Objective],
+        np: usize,
+        cr: f64,
+        factor: f64,
+        num_loop: usize,
+        init: Init,
+        mut rng: StdRng,
+        debug: bool,
+        verbose: bool,
+    ) -> Result<Self, String> {
+        let seeds = match init {
+            Init::Random => vec![],
+            _ => qmc::points(init, map.dims(), np, &mut rng)?
+                .iter()
+                .map(|point| map.at(point))
+                .collect(),
+        };
+        Ok(DifferentialEvolution {
+            map: map.clone(),
+            objectives: objectives.to_vec(),
+            np,
+            cr,
+            factor,
+            num_loop,
+            debug,
+            verbose,
+            generation: 0,
+            seeds,
+            pool: vec![],
+            rng,
+            queue: VecDeque::new(),
+            running: 0,
+            restored: 0,
+            best: Best::new(objectives),
+        })
+    }
+
+    /// Eliminate Top Seeds (by non-dominated sorting)
+    fn select(&mut self) {
+        pareto::sort(&self.objectives, &mut self.pool);
+        self.pool.truncate(self.np);
+    }
+
+    /// Set Jobs Queue for the next generation
+    fn fill(&mut self) {
+        if self.pool.len() < 3 {
+            // Random (or quasi-random) Seeds to Fill Pool
+            let mut seeds = std::mem::take(&mut self.seeds).into_iter();
+            for _ in 0..self.np {
+                let param = match seeds.next() {
+                    Some(param) if self.map.feasible(&param) => param,
+                    _ => self.map.rand(&mut self.rng),
+                };
+                if self.debug {
+                    eprintln!("Random Param: {:?}", &param);
+                }
+                self.queue.push_back(param);
+            }
+        } else {
+            // Evolution
+            for (x, _) in self.pool.iter() {
+                let indices = sample(&self.pool, 3, &mut self.rng);
+                let a = &self.pool[indices[0]].0;
+                let b = &self.pool[indices[1]].0;
+                let c = &self.pool[indices[2]].0;
+                let z = cross(x, a, b, c, &self.map, self.cr, self.factor, &mut self.rng);
+                if self.debug {
+                    eprintln!("DE: {:?} + ({:?}, {:?}, {:?}) => {:?}", &x, &a, &b, &c, &z);
+                }
+                self.queue.push_back(z);
+            }
+        }
+    }
+}
+
+impl Optimizer for DifferentialEvolution {
+    fn propose(&mut self) -> Option<Param> {
+        if self.queue.is_empty() && self.running == 0 {
+            if self.generation > self.num_loop {
+                return None;
+            }
+            if self.generation > 0 {
+                self.select();
+                if self.debug || self.verbose {
+                    if let Some(top) = self.pool.first() {
+                        eprintln!("The {}-th Generation Top: {:?}", self.generation - 1, top);
+                    }
+                }
+            }
+            if self.debug || self.verbose {
+                eprintln!("# Generation: {}", self.generation);
+            }
+            self.fill();
+            self.generation += 1;
+        }
+        let param = self.queue.pop_front()?;
+        self.running += 1;
+        Some(param)
+    }
+
+    fn observe(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        self.running -= 1;
+        if let Some(metrics) = result {
+            self.pool.push((param.clone(), metrics.clone()));
+            self.best.update(param, Some(metrics));
+        }
+    }
+
+    fn restore(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        // for logs without the generation
+        self.restored += 1;
+        self.generation = self.restored / std::cmp::max(self.np, 1);
+        self.running += 1;
+        self.observe(param, result);
+    }
+
+    fn progress(&self) -> Option<usize> {
+        Some(self.generation)
+    }
+
+    fn resume(&mut self, progress: usize) {
+        self.generation = std::cmp::max(self.generation, progress);
+    }
+
+    fn best(&self) -> Vec<(Param, Vec<Metric>)> {
+        self.best.get()
+    }
+}
+
+/// Steady-state (asynchronous) DE
+///
+/// Each target of the population has at most one trial (its mutant) running.
+/// A finished trial competes against its target at once and replaces it unless dominated,
+/// and then the next target gets a new mutant; no trial waits for a whole generation.
+/// The budget is the same as the generation-synchronous one, np * (loop + 1) trials.
+pub struct SteadyStateDE {
+    map: Map,
+    objectives: Vec<Objective>,
+    np: usize,
+    cr: f64,
+    factor: f64,
+    budget: usize,
+    debug: bool,
+    verbose: bool,
+    /// initial seeds from --init
+    seeds: VecDeque<Param>,
+    /// the population; None until its first trial is completed
+    targets: Vec<Option<(Param, Vec<Metric>)>>,
+    /// running trials and their targets
+    running: Vec<(Param, usize)>,
+    /// the next target to mutate
+    cursor: usize,
+    proposed: usize,
+    rng: StdRng,
+    best: Best,
+}
+
+impl SteadyStateDE {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        map: &Map,
+        objectives: &[Objective],
+        np: usize,
+        cr: f64,
+        factor: f64,
+        num_loop: usize,
+        init: Init,
+        mut rng: StdRng,
+        debug: bool,
+        verbose: bool,
+    ) -> Result<Self, String> {
+        let seeds = match init {
+            Init::Random => VecDeque::new(),
+            _ => qmc::points(init, map.dims(), np, &mut rng)?
+                .iter()
+                .map(|point| map.at(point))
+                .collect(),
+        };
+        let np = std::cmp::max(np, 1);
+        Ok(SteadyStateDE {
+            map: map.clone(),
+            objectives: objectives.to_vec(),
+            np,
+            cr,
+            factor,
+            budget: np * (num_loop + 1),
+            debug,
+            verbose,
+            seeds,
+            targets: vec![None; np],
+            running: vec![],
+            cursor: 0,
+            proposed: 0,
+            rng,
+            best: Best::new(objectives),
+        })
+    }
+
+    fn is_running(&self, k: usize) -> bool {
+        self.running.iter().any(|(_, target)| *target == k)
+    }
+
+    /// Random (or quasi-random) param for an empty target
+    fn seed(&mut self) -> Param {
+        let param = match self.seeds.pop_front() {
+            Some(param) if self.map.feasible(&param) => param,
+            _ => self.map.rand(&mut self.rng),
+        };
+        if self.debug {
+            eprintln!("Random Param: {:?}", &param);
+        }
+        param
+    }
+
+    /// Mutant of the k-th target with three other members
+    fn mutant(&mut self, k: usize) -> Option<Param> {
+        let others: Vec<usize> = (0..self.np)
+            .filter(|&i| i != k && self.targets[i].is_some())
+            .collect();
+        if others.len() < 3 {
+            return None;
+        }
+        let indices = sample(&others, 3, &mut self.rng);
+        let member = |i: usize| &self.targets[others[indices[i]]].as_ref().unwrap().0;
+        let (a, b, c) = (member(0), member(1), member(2));
+        let x = &self.targets[k].as_ref().unwrap().0;
+        let z = cross(x, a, b, c, &self.map, self.cr, self.factor, &mut self.rng);
+        if self.debug {
+            eprintln!("DE: {:?} + ({:?}, {:?}, {:?}) => {:?}", &x, &a, &b, &c, &z);
+        }
+        Some(z)
+    }
+
+    /// The trial (for the k-th target) against the target
+    fn compete(&mut self, k: usize, param: Param, metrics: Vec<Metric>) {
+        let replace = match &self.targets[k] {
+            Some((_, target)) => !pareto::dominates(&self.objectives, target, &metrics),
+            None => true,
+        };
+        if replace {
+            if self.debug || self.verbose {
+                eprintln!("Target {}: {:?} {:?}", k, &param, &metrics);
+            }
+            self.targets[k] = Some((param, metrics));
+        }
+    }
+}
+
+impl Optimizer for SteadyStateDE {
+    fn propose(&mut self) -> Option<Param> {
+        if self.proposed >= self.budget {
+            return None;
+        }
+        let mut next = None;
+        // empty targets first (initially, or after failures)
+        if let Some(k) = (0..self.np).find(|&k| self.targets[k].is_none() && !self.is_running(k)) {
+            next = Some((self.seed(), k));
+        }
+        for offset in 0..self.np {
+            if next.is_some() {
+                break;
+            }
+            let k = (self.cursor + offset) % self.np;
+            if self.targets[k].is_some() && !self.is_running(k) {
+                if let Some(z) = self.mutant(k) {
+                    self.cursor = (k + 1) % self.np;
+                    next = Some((z, k));
+                } else if self.running.is_empty() {
+                    // too few members to mutate, and nothing to wait for
+                    self.cursor = (k + 1) % self.np;
+                    next = Some((self.seed(), k));
+                }
+            }
+        }
+        // otherwise, wait for running trials
+        let (param, k) = next?;
+        self.proposed += 1;
+        self.running.push((param.clone(), k));
+        Some(param)
+    }
+
+    fn observe(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        let k = match self.running.iter().position(|(p, _)| p == &param) {
+            Some(i) => self.running.remove(i).1,
+            None => return,
+        };
+        if let Some(metrics) = result {
+            self.best.update(param.clone(), Some(metrics.clone()));
+            self.compete(k, param, metrics);
+        }
+    }
+
+    fn restore(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        let k = self.proposed % self.np;
+        self.proposed += 1;
+        self.running.push((param.clone(), k));
+        self.observe(param, result);
+    }
+
+    fn progress(&self) -> Option<usize> {
+        Some(self.proposed)
+    }
+
+    fn resume(&mut self, progress: usize) {
+        self.proposed = std::cmp::max(self.proposed, progress);
+    }
+
+    fn best(&self) -> Vec<(Param, Vec<Metric>)> {
+        self.best.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categorical_mutation() {
+        let mut rng = rand::thread_rng();
+        let choices: Vec<String> = ["adam", "sgd", "rmsprop"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let val = |k: usize| Value::Val(choices[k].clone());
+        for _ in 0..100 {
+            assert_eq!(
+                mutate_choice(&choices, &val(0), &val(1), &val(1), 1.0, &mut rng),
+                val(0)
+            );
+            assert_eq!(
+                mutate_choice(&choices, &val(0), &val(1), &val(2), 0.0, &mut rng),
+                val(0)
+            );
+            assert_ne!(
+                mutate_choice(&choices, &val(0), &val(1), &val(2), 1.0, &mut rng),
+                val(0)
+            );
+        }
+    }
+
+    #[test]
+    fn steady_state_budget() {
+        let mut map = Map::new();
+        map.add(String::from("X"), Value::IntRange(0, 100, 1));
+        let objs = [Objective::Minimize];
+        let metric = |x: &Param| match x[0].1 {
+            Value::Int(x) => vec![Metric {
+                metric: String::from("x"),
+                value: x as f64,
+                step: None,
+            }],
+            _ => panic!(),
+        };
+        let rng = crate::util::seeded(0);
+        let mut de =
+            SteadyStateDE::new(&map, &objs, 4, 0.5, 0.5, 9, Init::Random, rng, false, false)
+                .unwrap();
+        // the initial population is running; nothing to mutate yet
+        let initial: Vec<Param> = (0..4).map(|_| de.propose().unwrap()).collect();
+        assert!(de.propose().is_none());
+        for x in initial {
+            let m = metric(&x);
+            de.observe(x, Some(m));
+        }
+        let mut n = 4;
+        while let Some(x) = de.propose() {
+            let m = metric(&x);
+            de.observe(x, Some(m));
+            n += 1;
+        }
+        assert_eq!(n, 4 * 10);
+        assert_eq!(de.best().len(), 1);
+    }
+
+    #[test]
+    fn generational_budget() {
+        let mut map = Map::new();
+        map.add(String::from("X"), Value::IntRange(0, 100, 1));
+        let objs = [Objective::Minimize];
+        let metric = |x: &Param| match x[0].1 {
+            Value::Int(x) => vec![Metric {
+                metric: String::from("x"),
+                value: x as f64,
+                step: None,
+            }],
+            _ => panic!(),
+        };
+        let rng = crate::util::seeded(0);
+        let mut de = DifferentialEvolution::new(
+            &map,
+            &objs,
+            4,
+            0.5,
+            0.5,
+            2,
+            Init::Random,
+            rng,
+            false,
+            false,
+        )
+        .unwrap();
+        let mut n = 0;
+        loop {
+            // a whole generation is proposed at once
+            let generation: Vec<Param> = (0..4).filter_map(|_| de.propose()).collect();
+            if generation.is_empty() {
+                break;
+            }
+            assert_eq!(generation.len(), 4);
+            // the next one waits for every running trial
+            assert!(de.propose().is_none());
+            n += generation.len();
+            for x in generation {
+                let m = metric(&x);
+                de.observe(x, Some(m));
+            }
+        }
+        assert_eq!(n, 4 * 3);
+        assert!(de.propose().is_none());
+
+        // resumed at the last generation; the other trials were cache hits (without logs)
+        let rng = crate::util::seeded(0);
+        let mut de = DifferentialEvolution::new(
+            &map,
+            &objs,
+            4,
+            0.5,
+            0.5,
+            2,
+            Init::Random,
+            rng,
+            false,
+            false,
+        )
+        .unwrap();
+        for x in [0, 1, 2, 3, 4, 5] {
+            let x = vec![(String::from("X"), Value::Int(x))];
+            let m = metric(&x);
+            de.restore(x, Some(m));
+        }
+        de.resume(3);
+        assert!(de.propose().is_none());
+    }
+}
@@ -0,0 +1,203 @@
+/// Continuous Distributions (`KEY~U(0,1)`, `KEY~N(0,0.1)`, `KEY~LogU(1e-5,1e-1)`, ...)
+///
+///   U(a, b)        uniform on [a, b]
+///   LogU(a, b)     log-uniform on [a, b] (0 < a)
+///   N(mu, sigma)   normal, optionally truncated as N(mu, sigma, low, high)
+///   LogN(mu, sigma) log-normal (mu, sigma on the log scale),
+///                  optionally truncated as LogN(mu, sigma, low, high)
+extern crate rand;
+use rand::Rng;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dist {
+    Uniform(f64, f64),
+    LogUniform(f64, f64),
+    /// mu, sigma, low, high (infinite unless truncated)
+    Normal(f64, f64, f64, f64),
+    LogNormal(f64, f64, f64, f64),
+}
+
+/// Standard normal CDF (Abramowitz & Stegun 26.2.17)
+fn cdf(x: f64) -> f64 {
+    if x.is_infinite() {
+        return if x > 0.0 { 1.0 } else { 0.0 };
+    }
+    let t = 1.0 / (1.0 + 0.2316419 * x.abs());
+    let poly = t
+        * (0.319381530
+            + t * (-0.356563782 + t * (1.781477937 + t * (-1.821255978 + t * 1.330274429))));
+    let upper = (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt() * poly;
+    if x >= 0.0 {
+        1.0 - upper
+    } else {
+        upper
+    }
+}
+
+/// Inverse of the standard normal CDF (Acklam)
+fn inverse_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    let p = p.clamp(1e-300, 1.0 - 1e-16);
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Quantile of the normal distribution truncated on [low, high]
+fn normal_quantile(mu: f64, sigma: f64, low: f64, high: f64, p: f64) -> f64 {
+    let (a, b) = (cdf((low - mu) / sigma), cdf((high - mu) / sigma));
+    (mu + sigma * inverse_cdf(a + p * (b - a))).clamp(low, high)
+}
+
+impl Dist {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let err = || {
+            format!(
+                "Distribution should be U(a,b), LogU(a,b), N(mu,sigma[,low,high]) or LogN(mu,sigma[,low,high]): {:?}",
+                input
+            )
+        };
+        let (name, args) = input.trim().split_once('(').ok_or_else(err)?;
+        let args = args.strip_suffix(')').ok_or_else(err)?;
+        let args = args
+            .split(',')
+            .map(|x| {
+                x.trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("Cannot parse as number: {:?}", x))
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+        let dist = match (name, args.as_slice()) {
+            ("U", &[a, b]) => Dist::Uniform(a, b),
+            ("LogU", &[a, b]) => Dist::LogUniform(a, b),
+            ("N", &[mu, sigma]) => Dist::Normal(mu, sigma, f64::NEG_INFINITY, f64::INFINITY),
+            ("N", &[mu, sigma, low, high]) => Dist::Normal(mu, sigma, low, high),
+            ("LogN", &[mu, sigma]) => Dist::LogNormal(mu, sigma, 0.0, f64::INFINITY),
+            ("LogN", &[mu, sigma, low, high]) => Dist::LogNormal(mu, sigma, low, high),
+            _ => return Err(err()),
+        };
+        dist.validate().map(|_| dist)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let ok = match *self {
+            Dist::Uniform(a, b) => a < b,
+            Dist::LogUniform(a, b) => 0.0 < a && a < b,
+            Dist::Normal(mu, sigma, low, high) => {
+                sigma > 0.0
+                    && low < high
+                    && cdf((high - mu) / sigma) - cdf((low - mu) / sigma) > 1e-12
+            }
+            Dist::LogNormal(mu, sigma, low, high) => {
+                sigma > 0.0
+                    && 0.0 <= low
+                    && low < high
+                    && cdf((high.ln() - mu) / sigma) - cdf((low.ln() - mu) / sigma) > 1e-12
+            }
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(format!(
+                "Invalid parameters of the distribution: {:?}",
+                self
+            ))
+        }
+    }
+
+    /// Is it natural on the log scale?
+    pub fn is_log(&self) -> bool {
+        matches!(self, Dist::LogUniform(..) | Dist::LogNormal(..))
+    }
+
+    /// x such that P(X <= x) = p
+    pub fn quantile(&self, p: f64) -> f64 {
+        match *self {
+            Dist::Uniform(a, b) => a + (b - a) * p,
+            Dist::LogUniform(a, b) => (a.ln() + (b.ln() - a.ln()) * p).exp(),
+            Dist::Normal(mu, sigma, low, high) => normal_quantile(mu, sigma, low, high, p),
+            Dist::LogNormal(mu, sigma, low, high) => {
+                normal_quantile(mu, sigma, low.ln(), high.ln(), p).exp()
+            }
+        }
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        self.quantile(rng.gen::<f64>())
+    }
+
+    /// Clip into the support
+    pub fn clip(&self, x: f64) -> f64 {
+        match *self {
+            Dist::Uniform(a, b) | Dist::LogUniform(a, b) => x.clamp(a, b),
+            Dist::Normal(_, _, low, high) => x.clamp(low, high),
+            Dist::LogNormal(_, _, low, high) => x.clamp(low.max(f64::MIN_POSITIVE), high),
+        }
+    }
+
+    /// Finite range covering almost all the mass (for density estimation)
+    pub fn range(&self) -> (f64, f64) {
+        (self.quantile(1e-4), self.quantile(1.0 - 1e-4))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dist_quantile() {
+        let close = |x: f64, y: f64| (x - y).abs() < 1e-5;
+        assert!(close(inverse_cdf(cdf(1.5)), 1.5));
+        assert!(close(Dist::parse("N(1,2)").unwrap().quantile(0.5), 1.0));
+        assert!(close(Dist::parse("U(0, 10)").unwrap().quantile(0.3), 3.0));
+        assert!(close(
+            Dist::parse("LogU(1e-5,1e-1)").unwrap().quantile(0.5),
+            1e-3
+        ));
+        let truncated = Dist::parse("N(0,1,0,1)").unwrap();
+        assert!(truncated.quantile(0.0) >= 0.0 && truncated.quantile(1.0) <= 1.0);
+        assert!(Dist::parse("U(1,0)").is_err());
+        assert!(Dist::parse("N(0,-1)").is_err());
+        assert!(Dist::parse("Q(0,1)").is_err());
+    }
+}
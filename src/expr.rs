@@ -0,0 +1,336 @@
+/// Small Expression Language over Params
+///
+///   expr := or
+///   or   := and ("||" and)*
+///   and  := not ("&&" not)*
+///   not  := "!" not | cmp
+///   cmp  := sum (("==" | "!=" | "<=" | ">=" | "<" | ">") sum)?
+///   sum  := prod (("+" | "-") prod)*
+///   prod := unary (("*" | "/" | "%") unary)*
+///   unary := "-" unary | atom
+///   atom := number | 'string' | KEY | "(" expr ")"
+///
+/// Arithmetic of two integers stays integer (e.g. `7 / 2 == 3`).
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::{char, multispace0},
+    combinator::{map, opt, recognize},
+    number::complete::recognize_float,
+    sequence::{delimited, pair, preceded},
+    IResult,
+};
+
+use crate::map::{Param, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Scalar {
+    fn from_value(val: &Value) -> Option<Self> {
+        match val {
+            Value::Int(x) => Some(Scalar::Int(*x)),
+            Value::Float(x) => Some(Scalar::Float(*x)),
+            Value::Val(x) => Some(if let Ok(x) = x.parse::<i64>() {
+                Scalar::Int(x)
+            } else if let Ok(x) = x.parse::<f64>() {
+                Scalar::Float(x)
+            } else {
+                Scalar::Str(x.clone())
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Scalar::Int(x) => Value::Int(*x),
+            Scalar::Float(x) => Value::Float(*x),
+            Scalar::Str(x) => Value::Val(x.clone()),
+            Scalar::Bool(b) => Value::Val(b.to_string()),
+        }
+    }
+
+    fn as_f64(&self) -> Result<f64, String> {
+        match self {
+            Scalar::Int(x) => Ok(*x as f64),
+            Scalar::Float(x) => Ok(*x),
+            _ => Err(format!("Not a number: {:?}", self)),
+        }
+    }
+
+    fn truthy(&self) -> Result<bool, String> {
+        match self {
+            Scalar::Bool(b) => Ok(*b),
+            Scalar::Int(x) => Ok(*x != 0),
+            Scalar::Float(x) => Ok(*x != 0.0),
+            Scalar::Str(_) => Err(format!("Not a boolean: {:?}", self)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Or,
+    And,
+    Not,
+    Neg,
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Lit(Scalar),
+    Var(String),
+    Unary(Op, Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+fn ws<'a, O>(
+    inner: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(multispace0, inner, multispace0)
+}
+
+fn number(input: &str) -> IResult<&str, Expr> {
+    map(recognize_float, |s: &str| {
+        if let Ok(x) = s.parse::<i64>() {
+            Expr::Lit(Scalar::Int(x))
+        } else {
+            Expr::Lit(Scalar::Float(s.parse::<f64>().unwrap()))
+        }
+    })(input)
+}
+
+fn string(input: &str) -> IResult<&str, Expr> {
+    map(
+        delimited(char('\''), take_while(|c| c != '\''), char('\'')),
+        |s: &str| Expr::Lit(Scalar::Str(s.to_string())),
+    )(input)
+}
+
+fn ident(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        take_while1(|c: char| c.is_ascii_alphabetic() || c == '_'),
+        take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+    ))(input)
+}
+
+fn atom(input: &str) -> IResult<&str, Expr> {
+    ws(alt((
+        number,
+        string,
+        map(ident, |s: &str| Expr::Var(s.to_string())),
+        delimited(char('('), or, char(')')),
+    )))(input)
+}
+
+fn unary(input: &str) -> IResult<&str, Expr> {
+    alt((
+        map(preceded(ws(char('-')), unary), |e| {
+            Expr::Unary(Op::Neg, Box::new(e))
+        }),
+        atom,
+    ))(input)
+}
+
+/// Left-associative chain of binary operators
+fn chain<'a>(
+    input: &'a str,
+    operand: fn(&'a str) -> IResult<&'a str, Expr>,
+    ops: &[(&'static str, Op)],
+) -> IResult<&'a str, Expr> {
+    let (mut input, mut lhs) = operand(input)?;
+    'outer: loop {
+        for &(symbol, op) in ops {
+            if let Ok((rest, rhs)) = preceded(ws(tag(symbol)), operand)(input) {
+                lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+                input = rest;
+                continue 'outer;
+            }
+        }
+        return Ok((input, lhs));
+    }
+}
+
+fn prod(input: &str) -> IResult<&str, Expr> {
+    chain(
+        input,
+        unary,
+        &[("*", Op::Mul), ("/", Op::Div), ("%", Op::Rem)],
+    )
+}
+
+fn sum(input: &str) -> IResult<&str, Expr> {
+    chain(input, prod, &[("+", Op::Add), ("-", Op::Sub)])
+}
+
+fn cmp(input: &str) -> IResult<&str, Expr> {
+    let (input, lhs) = sum(input)?;
+    let ops = alt((
+        map(tag("=="), |_| Op::Eq),
+        map(tag("!="), |_| Op::Ne),
+        map(tag("<="), |_| Op::Le),
+        map(tag(">="), |_| Op::Ge),
+        map(tag("<"), |_| Op::Lt),
+        map(tag(">"), |_| Op::Gt),
+    ));
+    let (input, rhs) = opt(pair(ws(ops), sum))(input)?;
+    match rhs {
+        Some((op, rhs)) => Ok((input, Expr::Binary(op, Box::new(lhs), Box::new(rhs)))),
+        None => Ok((input, lhs)),
+    }
+}
+
+fn not(input: &str) -> IResult<&str, Expr> {
+    alt((
+        map(preceded(ws(char('!')), not), |e| {
+            Expr::Unary(Op::Not, Box::new(e))
+        }),
+        cmp,
+    ))(input)
+}
+
+fn and(input: &str) -> IResult<&str, Expr> {
+    chain(input, not, &[("&&", Op::And)])
+}
+
+fn or(input: &str) -> IResult<&str, Expr> {
+    chain(input, and, &[("||", Op::Or)])
+}
+
+impl Expr {
+    pub fn parse(input: &str) -> Result<Self, String> {
+        match or(input) {
+            Ok((rest, expr)) if rest.trim().is_empty() => Ok(expr),
+            Ok((rest, _)) => Err(format!("Parse Error at {:?} in {:?}", rest, input)),
+            Err(_) => Err(format!("Parse Error: {:?}", input)),
+        }
+    }
+
+    /// Keys used in the expression
+    pub fn vars(&self) -> Vec<String> {
+        match self {
+            Expr::Lit(_) => vec![],
+            Expr::Var(name) => vec![name.clone()],
+            Expr::Unary(_, e) => e.vars(),
+            Expr::Binary(_, lhs, rhs) => {
+                let mut vs = lhs.vars();
+                vs.extend(rhs.vars());
+                vs
+            }
+        }
+    }
+
+    pub fn eval(&self, param: &Param) -> Result<Scalar, String> {
+        use Scalar::*;
+        match self {
+            Expr::Lit(x) => Ok(x.clone()),
+            Expr::Var(name) => param
+                .iter()
+                .rev()
+                .find(|(key, _)| key == name)
+                .and_then(|(_, val)| Scalar::from_value(val))
+                .ok_or(format!("Unknown key: {}", name)),
+            Expr::Unary(Op::Not, e) => Ok(Bool(!e.eval(param)?.truthy()?)),
+            Expr::Unary(_, e) => match e.eval(param)? {
+                Int(x) => Ok(Int(-x)),
+                x => Ok(Float(-x.as_f64()?)),
+            },
+            Expr::Binary(Op::And, lhs, rhs) => Ok(Bool(
+                lhs.eval(param)?.truthy()? && rhs.eval(param)?.truthy()?,
+            )),
+            Expr::Binary(Op::Or, lhs, rhs) => Ok(Bool(
+                lhs.eval(param)?.truthy()? || rhs.eval(param)?.truthy()?,
+            )),
+            Expr::Binary(op, lhs, rhs) => {
+                let (x, y) = (lhs.eval(param)?, rhs.eval(param)?);
+                match (op, &x, &y) {
+                    (Op::Eq, Str(a), Str(b)) => Ok(Bool(a == b)),
+                    (Op::Ne, Str(a), Str(b)) => Ok(Bool(a != b)),
+                    (Op::Eq, _, _) => Ok(Bool(x.as_f64()? == y.as_f64()?)),
+                    (Op::Ne, _, _) => Ok(Bool(x.as_f64()? != y.as_f64()?)),
+                    (Op::Le, _, _) => Ok(Bool(x.as_f64()? <= y.as_f64()?)),
+                    (Op::Ge, _, _) => Ok(Bool(x.as_f64()? >= y.as_f64()?)),
+                    (Op::Lt, _, _) => Ok(Bool(x.as_f64()? < y.as_f64()?)),
+                    (Op::Gt, _, _) => Ok(Bool(x.as_f64()? > y.as_f64()?)),
+                    (Op::Div | Op::Rem, Int(_), Int(0)) => Err("Division by zero".to_string()),
+                    (_, Int(a), Int(b)) => Ok(Int(match op {
+                        Op::Add => a + b,
+                        Op::Sub => a - b,
+                        Op::Mul => a * b,
+                        Op::Div => a / b,
+                        _ => a % b,
+                    })),
+                    _ => {
+                        let (a, b) = (x.as_f64()?, y.as_f64()?);
+                        Ok(Float(match op {
+                            Op::Add => a + b,
+                            Op::Sub => a - b,
+                            Op::Mul => a * b,
+                            Op::Div => a / b,
+                            _ => a % b,
+                        }))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Evaluate as a condition
+    pub fn test(&self, param: &Param) -> Result<bool, String> {
+        self.eval(param)?.truthy()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param() -> Param {
+        vec![
+            (String::from("DIM"), Value::Int(512)),
+            (String::from("HEADS"), Value::Val(String::from("6"))),
+            (String::from("LR"), Value::Float(0.5)),
+            (String::from("OPT"), Value::Val(String::from("sgd"))),
+        ]
+    }
+
+    #[test]
+    fn expr_eval() {
+        let eval = |s: &str| Expr::parse(s).unwrap().eval(&param());
+        assert_eq!(eval("DIM % HEADS"), Ok(Scalar::Int(2)));
+        assert_eq!(eval("DIM / 10"), Ok(Scalar::Int(51)));
+        assert_eq!(eval("LR * 2"), Ok(Scalar::Float(1.0)));
+        assert_eq!(eval("-(1 + 2) * 3"), Ok(Scalar::Int(-9)));
+        assert_eq!(eval("1 + 2 * 3 - 4"), Ok(Scalar::Int(3)));
+        assert_eq!(eval("OPT == 'sgd' && LR < 1e-1"), Ok(Scalar::Bool(false)));
+        assert_eq!(
+            eval("!(DIM % HEADS == 0) || OPT != 'sgd'"),
+            Ok(Scalar::Bool(true))
+        );
+        assert!(eval("NONE + 1").is_err());
+    }
+
+    #[test]
+    fn expr_parse_error() {
+        assert!(Expr::parse("DIM %").is_err());
+        assert!(Expr::parse("(DIM").is_err());
+        assert!(Expr::parse("DIM HEADS").is_err());
+    }
+}
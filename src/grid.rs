@@ -0,0 +1,72 @@
+/// Brute-force (Grid Search)
+use std::collections::HashSet;
+
+extern crate rand;
+use rand::rngs::StdRng;
+
+use crate::map::{Map, MapIter, Param};
+use crate::metric::Metric;
+use crate::optimizer::{Best, Optimizer};
+use crate::options::Objective;
+use crate::permutation::{Order, Permutation};
+
+/// Enumerates the grid in the `order`, up to `limit` points
+pub struct Grid {
+    map: Map,
+    iter: MapIter,
+    limit: Option<usize>,
+    done: HashSet<Vec<String>>,
+    best: Best,
+}
+
+impl Grid {
+    pub fn new(
+        map: &Map,
+        objectives: &[Objective],
+        order: Order,
+        limit: Option<usize>,
+        mut rng: StdRng,
+    ) -> Result<Self, String> {
+        if let Some((key, _)) = map.data.iter().find(|(_, val)| val.is_continuous()) {
+            return Err(format!(
+                "Grid cannot enumerate the continuous `{}`; use --discretize N or another optimizer",
+                key
+            ));
+        }
+        let size = map
+            .size()
+            .ok_or("The grid has too many points to enumerate; use another optimizer")?;
+        Ok(Grid {
+            map: map.clone(),
+            iter: map.iter_by(Permutation::new(order, size, &mut rng)),
+            limit,
+            done: HashSet::new(),
+            best: Best::new(objectives),
+        })
+    }
+}
+
+impl Optimizer for Grid {
+    fn propose(&mut self) -> Option<Param> {
+        if self.limit.is_some_and(|limit| self.done.len() >= limit) {
+            return None;
+        }
+        let (map, done) = (&self.map, &self.done);
+        let param = self.iter.find(|param| !done.contains(&map.args(param)))?;
+        self.done.insert(self.map.args(&param));
+        Some(param)
+    }
+
+    fn observe(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        self.best.update(param, result);
+    }
+
+    fn restore(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        self.done.insert(self.map.args(&param));
+        self.observe(param, result);
+    }
+
+    fn best(&self) -> Vec<(Param, Vec<Metric>)> {
+        self.best.get()
+    }
+}
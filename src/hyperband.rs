@@ -0,0 +1,256 @@
+/// Hyperband (Successive Halving over brackets)
+use std::collections::VecDeque;
+
+extern crate rand;
+use rand::rngs::StdRng;
+
+use crate::map::{Map, Param, Value};
+use crate::metric::Metric;
+use crate::optimizer::{Best, Optimizer};
+use crate::options::Objective;
+use crate::pareto;
+
+/// One key of the Map is the resource budget (e.g. EPOCHS=1..81).
+/// Each bracket samples configurations with `Map::rand`, runs them with a small budget,
+/// and promotes the top 1/eta to eta times larger budget until the max budget.
+pub struct Hyperband {
+    map: Map,
+    objectives: Vec<Objective>,
+    budget_index: usize,
+    min_budget: f64,
+    max_budget: f64,
+    is_int: bool,
+    eta: usize,
+    s_max: usize,
+    /// remaining brackets
+    brackets: VecDeque<usize>,
+    /// current bracket and rung
+    s: usize,
+    rung: usize,
+    queue: VecDeque<Param>,
+    running: usize,
+    results: Vec<(Param, Vec<Metric>)>,
+    rng: StdRng,
+    best: Best,
+}
+
+impl Hyperband {
+    pub fn new(
+        map: &Map,
+        objectives: &[Objective],
+        budget: &str,
+        eta: usize,
+        rng: StdRng,
+    ) -> Result<Self, String> {
+        let budget_index = map
+            .data
+            .iter()
+            .position(|(key, _)| key == budget)
+            .ok_or(format!("Budget `{}` is not in the mappings", budget))?;
+        let (min_budget, max_budget, is_int) = match map.data[budget_index].1 {
+            Value::IntRange(begin, end, _) | Value::IntLogRange(begin, end, _) => {
+                (begin as f64, end as f64, true)
+            }
+            Value::FloatRange(begin, end, _) | Value::FloatLogRange(begin, end, _) => {
+                (begin, end, false)
+            }
+            _ => {
+                return Err(format!(
+                    "Budget `{}` should be a range (e.g. 1..81)",
+                    budget
+                ))
+            }
+        };
+        if eta < 2 || min_budget <= 0.0 || min_budget > max_budget {
+            return Err("Hyperband needs eta >= 2 and 0 < min budget <= max budget".to_string());
+        }
+        let ratio = max_budget / min_budget;
+        let s_max = (ratio.ln() / (eta as f64).ln() + 1e-9).floor() as usize;
+        Ok(Hyperband {
+            map: map.clone(),
+            objectives: objectives.to_vec(),
+            budget_index,
+            min_budget,
+            max_budget,
+            is_int,
+            eta,
+            s_max,
+            brackets: (0..s_max + 1).rev().collect(),
+            s: 0,
+            rung: 0,
+            queue: VecDeque::new(),
+            running: 0,
+            results: vec![],
+            rng,
+            best: Best::new(objectives),
+        })
+    }
+
+    /// Budget of the i-th rung in the bracket s
+    fn budget(&self, s: usize, i: usize) -> Value {
+        let b = self.max_budget * (self.eta as f64).powi(i as i32 - s as i32);
+        let b = b.max(self.min_budget);
+        if self.is_int {
+            Value::Int(b.round() as i64)
+        } else {
+            Value::Float(b)
+        }
+    }
+
+    fn with_budget(&self, mut param: Param, budget: &Value) -> Param {
+        param[self.budget_index].1 = budget.clone();
+        param
+    }
+
+    /// Start the next bracket
+    fn start(&mut self) -> bool {
+        match self.brackets.pop_front() {
+            None => false,
+            Some(s) => {
+                let eta = self.eta as f64;
+                let n = ((self.s_max + 1) as f64 / (s + 1) as f64 * eta.powi(s as i32)).ceil();
+                let budget = self.budget(s, 0);
+                eprintln!(
+                    "# Hyperband Bracket s={}: {} configs with {}={:?}",
+                    s, n, self.map.data[self.budget_index].0, budget
+                );
+                for _ in 0..n as usize {
+                    let param = self.map.rand(&mut self.rng);
+                    let param = self.with_budget(param, &budget);
+                    self.queue.push_back(param);
+                }
+                self.s = s;
+                self.rung = 0;
+                self.results.clear();
+                true
+            }
+        }
+    }
+
+    /// Promote the top 1/eta of the current rung; false at the end of the bracket
+    fn promote(&mut self) -> bool {
+        if self.rung >= self.s {
+            return false;
+        }
+        let n = self.results.len() / self.eta;
+        if n == 0 {
+            return false;
+        }
+        pareto::sort(&self.objectives, &mut self.results);
+        self.rung += 1;
+        let budget = self.budget(self.s, self.rung);
+        eprintln!(
+            "# Hyperband Promotion: {} configs to {}={:?}",
+            n, self.map.data[self.budget_index].0, budget
+        );
+        let promoted: Vec<Param> = self.results[..n]
+            .iter()
+            .map(|(param, _)| self.with_budget(param.clone(), &budget))
+            .collect();
+        self.queue.extend(promoted);
+        self.results.clear();
+        true
+    }
+}
+
+impl Optimizer for Hyperband {
+    fn propose(&mut self) -> Option<Param> {
+        while self.queue.is_empty() && self.running == 0 {
+            if !self.promote() && !self.start() {
+                return None;
+            }
+        }
+        let param = self.queue.pop_front()?;
+        self.running += 1;
+        Some(param)
+    }
+
+    fn observe(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        self.running -= 1;
+        if let Some(metrics) = result {
+            if self.rung == self.s {
+                self.best.update(param.clone(), Some(metrics.clone()));
+            }
+            self.results.push((param, metrics));
+        }
+    }
+
+    fn restore(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        // brackets are not reconstructed; only the best trials are kept
+        let full = self.with_budget(param.clone(), &self.budget(0, 0));
+        if full == param {
+            self.best.update(param, result);
+        }
+    }
+
+    fn best(&self) -> Vec<(Param, Vec<Metric>)> {
+        self.best.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hyperband_brackets() {
+        let mut map = Map::new();
+        map.add(String::from("EPOCHS"), Value::IntRange(1, 9, 1));
+        map.add(String::from("X"), Value::IntRange(0, 100, 1));
+        let int = |v: &Value| match v {
+            Value::Int(x) => *x,
+            _ => panic!(),
+        };
+        let loss = |x: i64| {
+            vec![Metric {
+                metric: String::from("loss"),
+                value: x as f64,
+                step: None,
+            }]
+        };
+        let rng = crate::util::seeded(0);
+        let mut hb = Hyperband::new(&map, &[Objective::Minimize], "EPOCHS", 3, rng).unwrap();
+        assert_eq!(hb.s_max, 2);
+
+        // run rung by rung: (budget, Xs)
+        let mut rungs: Vec<(i64, Vec<i64>)> = vec![];
+        loop {
+            let mut params = vec![];
+            while let Some(param) = hb.propose() {
+                params.push(param);
+            }
+            if params.is_empty() {
+                break;
+            }
+            let budget = int(&params[0][0].1);
+            assert!(params.iter().all(|p| int(&p[0].1) == budget));
+            rungs.push((budget, params.iter().map(|p| int(&p[1].1)).collect()));
+            for param in params {
+                let x = int(&param[1].1);
+                hb.observe(param, Some(loss(x)));
+            }
+        }
+
+        // n = ceil((s_max + 1) / (s + 1) * eta^s) configs with eta times larger budgets
+        let sizes: Vec<(i64, usize)> = rungs.iter().map(|(b, xs)| (*b, xs.len())).collect();
+        assert_eq!(sizes, vec![(1, 9), (3, 3), (9, 1), (3, 5), (9, 1), (9, 3)]);
+
+        // the top 1/eta are promoted
+        let mut first = rungs[0].1.clone();
+        first.sort_unstable();
+        let mut promoted = rungs[1].1.clone();
+        promoted.sort_unstable();
+        assert_eq!(promoted, first[..3].to_vec());
+
+        // the best only from the max budget
+        let best = hb.best();
+        let min_at_max = rungs
+            .iter()
+            .filter(|(b, _)| *b == 9)
+            .flat_map(|(_, xs)| xs.iter().copied())
+            .min()
+            .unwrap();
+        assert_eq!(int(&best[0].0[0].1), 9);
+        assert_eq!(best[0].1[0].value, min_at_max as f64);
+    }
+}
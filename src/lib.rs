@@ -1,2 +1,5 @@
+pub mod dist;
+pub mod expr;
 pub mod map;
 pub mod metric;
+pub mod permutation;
@@ -1,37 +1,106 @@
-use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
 
 extern crate serde;
 extern crate serde_json;
 use serde_json::json;
 
+extern crate rand;
+
+extern crate libc;
+
 extern crate chrono;
 use chrono::prelude::*;
 
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
 
+mod dist;
+mod expr;
 mod map;
-use map::*;
+use map::Param;
 mod metric;
-use metric::{average, Metric};
+use metric::{average_each, Metric};
 mod name;
 mod options;
 use options::*;
+mod optimizer;
+mod pareto;
+mod permutation;
+mod pool;
+use pool::Pool;
+mod prune;
+mod qmc;
+use prune::MedianPruner;
+mod resume;
+mod signal;
+mod space;
+mod trial;
+use trial::Status;
 mod util;
-use util::{sample, Total};
+use optimizer::{Optimizer, OptimizerKind};
+mod grid;
+use grid::Grid;
+mod random;
+use random::RandomSearch;
+mod cache;
+use cache::{Cache, Lookup};
 mod de;
-use de::cross;
+use de::{DifferentialEvolution, SteadyStateDE};
+mod tpe;
+use tpe::Tpe;
+mod hyperband;
+use hyperband::Hyperband;
 
 fn log_file_name(name: &String, id: usize) -> String {
     let now = Local::now();
     format!(".hake/log/{}_{}_{:08}", now.format("%Y%m%d"), name, id)
 }
 
+/// Fails the trial if its job unwinds before observing the result
+struct Unfinished {
+    trial: Option<(Vec<String>, Param)>,
+    cache: Arc<Mutex<Cache>>,
+    optimizer: Arc<Mutex<Box<dyn Optimizer>>>,
+}
+
+impl Unfinished {
+    /// The job has reached the end; hand the trial back to observe it
+    fn finish(mut self) -> (Vec<String>, Param) {
+        self.trial.take().unwrap()
+    }
+}
+
+impl Drop for Unfinished {
+    fn drop(&mut self) {
+        if let Some((key, param)) = self.trial.take() {
+            let waiting = self
+                .cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .finish(&key, None);
+            let mut optimizer = self
+                .optimizer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            for param in waiting {
+                optimizer.observe(param, None);
+            }
+            optimizer.observe(param, None);
+        }
+    }
+}
+
 fn make(opt: &Options) -> Result<(), String> {
-    let name = opt.name()?;
-    let (targets, map) = opt.target_map();
+    // no trial outlives hake, however make() returns
+    let _reaper = signal::Reaper;
+    let seed = opt.seed.unwrap_or_else(rand::random);
+    let name = opt.name(seed)?;
+    let (targets, map) = opt.target_map()?;
+    let kind = opt.optimizer()?;
 
     eprintln!("\x1b[33mName: {}\x1b[0m", &name);
 
@@ -43,198 +112,313 @@ fn make(opt: &Options) -> Result<(), String> {
 
     name::touch(&name).expect("Cannot put name file.");
 
-    match opt.metric() {
-        None => {
-            // Brute-force
-            eprintln!("\x1b[33mMetric: None\x1b[0m");
-            let name = Arc::new(name);
-            let args = Arc::new(args);
-            let mut handles = VecDeque::new();
-            let now = std::time::SystemTime::now();
-            for (id, param) in map.iter().enumerate() {
-                if let Ok(elapsed) = now.elapsed() {
-                    if opt.timeout > 0 && elapsed.as_secs() > opt.timeout {
-                        break;
-                    }
-                }
-                let name = name.clone();
-                let args = args.clone();
-                let handle = thread::spawn(move || {
-                    testone(&name, id, &args, &param, None);
-                });
-                handles.push_back(handle);
-                while handles.len() >= opt.parallels() {
-                    let handle_top = handles.pop_front().unwrap();
-                    handle_top.join().unwrap();
-                }
+    let objectives: Vec<Objective> = opt.objectives().iter().map(|(obj, _)| *obj).collect();
+    let metric_names: Vec<String> = opt.objectives().into_iter().map(|(_, name)| name).collect();
+    if objectives.is_empty() {
+        eprintln!("\x1b[33mMetric: None\x1b[0m");
+    }
+    for (obj, metric_name) in opt.objectives() {
+        eprintln!("\x1b[33m{:?}: {}\x1b[0m", obj, &metric_name);
+    }
+    eprintln!("\x1b[33mOptimizer: {:?}\x1b[0m", kind);
+    eprintln!("\x1b[33mSeed: {}\x1b[0m", seed);
+
+    let rng = util::seeded(seed);
+
+    let mut optimizer: Box<dyn Optimizer> = match kind {
+        OptimizerKind::Grid => Box::new(Grid::new(
+            &map,
+            &objectives,
+            opt.optimize.order,
+            opt.optimize.limit,
+            rng,
+        )?),
+        OptimizerKind::Random => Box::new(RandomSearch::new(
+            &map,
+            &objectives,
+            opt.optimize.trials.unwrap(),
+            opt.optimize.init,
+            rng,
+        )?),
+        OptimizerKind::DE if opt.optimize.steady_state => Box::new(SteadyStateDE::new(
+            &map,
+            &objectives,
+            opt.optimize.np,
+            opt.optimize.cr,
+            opt.optimize.factor,
+            opt.optimize.num_loop,
+            opt.optimize.init,
+            rng,
+            opt.debug,
+            opt.verbose,
+        )?),
+        OptimizerKind::DE => Box::new(DifferentialEvolution::new(
+            &map,
+            &objectives,
+            opt.optimize.np,
+            opt.optimize.cr,
+            opt.optimize.factor,
+            opt.optimize.num_loop,
+            opt.optimize.init,
+            rng,
+            opt.debug,
+            opt.verbose,
+        )?),
+        OptimizerKind::Tpe => Box::new(Tpe::new(
+            &map,
+            &objectives,
+            opt.optimize.trials.unwrap(),
+            opt.optimize.startup,
+            rng,
+        )),
+        OptimizerKind::Hyperband => Box::new(Hyperband::new(
+            &map,
+            &objectives,
+            opt.optimize.budget.as_ref().unwrap(),
+            opt.optimize.eta,
+            rng,
+        )?),
+    };
+    let mut hid = 0;
+    if let Some(resume) = &opt.resume {
+        let (trials, next_id, progress) = resume::load(resume, &map, &metric_names)?;
+        eprintln!(
+            "\x1b[33mResume: {} trials (next HID={})\x1b[0m",
+            trials.len(),
+            next_id
+        );
+        for (param, result) in trials {
+            optimizer.restore(param, result);
+        }
+        if let Some(progress) = progress {
+            optimizer.resume(progress);
+        }
+        hid = next_id;
+    }
+    let optimizer = Arc::new(Mutex::new(optimizer));
+
+    let git = git_hash();
+    let mut cache = Cache::new();
+    if opt.reuse_results {
+        let mut reused = 0;
+        for record in resume::records(&metric_names)? {
+            if record.result.is_some() {
+                cache.insert(
+                    Cache::key(&record.make_args, &record.git_hash),
+                    record.result,
+                );
+                reused += 1;
             }
-            // Wait Rest All
-            while let Some(handle) = handles.pop_front() {
-                handle.join().unwrap();
+        }
+        eprintln!("\x1b[33mReuse: {} results in .hake/log\x1b[0m", reused);
+    }
+    let cache = Arc::new(Mutex::new(cache));
+
+    let map = Arc::new(map);
+    let name = Arc::new(name);
+    let args = Arc::new(args);
+    let pruner = match opt.objectives().first() {
+        Some((obj, metric_name)) if opt.prune => {
+            Some(MedianPruner::new(*obj, metric_name, opt.prune_startup))
+        }
+        _ => None,
+    };
+    let pruner = Arc::new(pruner);
+    let metric_names = Arc::new(metric_names);
+    let retry = opt.retry;
+    let trial_timeout = if opt.trial_timeout > 0 {
+        Some(Duration::from_secs(opt.trial_timeout))
+    } else {
+        None
+    };
+    let num_samples = if !metric_names.is_empty() {
+        opt.metric_num_samples()
+    } else {
+        1
+    };
+
+    let mut pool = Pool::new(opt.parallels());
+    let now = std::time::SystemTime::now();
+    loop {
+        if let Ok(elapsed) = now.elapsed() {
+            if opt.timeout > 0 && elapsed.as_secs() > opt.timeout {
+                break;
             }
         }
-        Some((obj, metric_name)) => {
-            // Optimize by Differential Evolution
-            eprintln!("\x1b[33m{:?}: {}\x1b[0m", obj, &metric_name);
-            let name = Arc::new(name);
-            let args = Arc::new(args);
-            let metric_name = Arc::new(metric_name);
-            // DE vars
-            let pool: Arc<Mutex<Vec<(Param, Metric)>>> = Arc::new(Mutex::new(vec![]));
-            let id = Arc::new(Mutex::new(0));
-            let job_queue = Arc::new(Mutex::new(VecDeque::new()));
-
-            let now = std::time::SystemTime::now();
-            for gen in 0..opt.optimize.num_loop + 1 {
-                if let Ok(elapsed) = now.elapsed() {
-                    if opt.timeout > 0 && elapsed.as_secs() > opt.timeout {
-                        break;
-                    }
+        if let Some(sig) = signal::received() {
+            eprintln!(
+                "\x1b[33mInterrupted (signal {}); waiting for the running trials\x1b[0m",
+                sig
+            );
+            break;
+        }
+        let (next_job, progress) = {
+            let mut optimizer = optimizer.lock().unwrap();
+            (optimizer.propose(), optimizer.progress())
+        };
+        if let Some(param) = next_job {
+            let param_args = match map.make_args(&param) {
+                Ok(param_args) => param_args,
+                Err(e) => {
+                    // the trial cannot run without its derived keys
+                    eprintln!("\x1b[33m[Failed] {}: {:?}\x1b[0m", e, &param);
+                    optimizer.lock().unwrap().observe(param, None);
+                    continue;
                 }
-                if opt.debug || opt.verbose {
-                    eprintln!("# Generation: {}", gen);
+            };
+            let key = Cache::key(&[args.as_slice(), &param_args].concat(), &git);
+            let lookup = cache.lock().unwrap().lookup(&key, &param);
+            match lookup {
+                Lookup::Hit(result) => {
+                    eprintln!("\x1b[33m[Cached] {}\x1b[0m", param_args.join(" "));
+                    optimizer.lock().unwrap().observe(param, result);
+                    continue;
                 }
-                // Set Jobs Queue
-                let poolsize = pool.lock().unwrap().len();
-                if poolsize == 0 {
-                    // Random Seeds to Fill Pool
-                    for _ in 0..opt.optimize.np {
-                        let param = map.rand();
-                        if opt.debug {
-                            eprintln!("Random Param: {:?}", &param);
-                        }
-                        let mut q = job_queue.lock().unwrap();
-                        q.push_back(param);
-                    }
-                } else {
-                    let pool = pool.lock().unwrap();
-                    let mut q = job_queue.lock().unwrap();
-                    // Evolution
-                    for (x, _) in pool.iter() {
-                        let a;
-                        let b;
-                        let c;
-                        {
-                            let indices = sample(&pool, 3);
-                            let i = indices[0];
-                            let j = indices[1];
-                            let k = indices[2];
-                            a = &pool[i].0;
-                            b = &pool[j].0;
-                            c = &pool[k].0;
-                        }
-                        let z = cross(&x, &a, &b, &c, &map, opt.optimize.cr, opt.optimize.factor);
-                        if opt.debug {
-                            eprintln!("DE: {:?} + ({:?}, {:?}, {:?}) => {:?}", &x, &a, &b, &c, &z);
-                        }
-                        q.push_back(z);
-                    }
+                Lookup::Pending => {
+                    eprintln!(
+                        "\x1b[33m[Cached] {} (waiting for the running one)\x1b[0m",
+                        param_args.join(" ")
+                    );
+                    continue;
                 }
-
-                let mut handles = VecDeque::new();
-
-                // Parallel Max to -j
-                loop {
-                    if let Ok(elapsed) = now.elapsed() {
-                        if opt.timeout > 0 && elapsed.as_secs() > opt.timeout {
-                            break;
-                        }
-                    }
-                    let next_job = job_queue.lock().unwrap().pop_front();
-                    if let Some(param) = next_job {
-                        let name = name.clone();
-                        let args = args.clone();
-                        let metric_name = metric_name.clone();
-                        let metric_num_samples = opt.metric_num_samples().clone();
-                        let pool = pool.clone();
-                        let id = id.clone();
-                        let handle = thread::spawn(move || {
-                            let hid: usize;
-                            {
-                                let mut id = id.lock().unwrap();
-                                hid = (*id).clone();
-                                *id += 1;
-                            }
-                            let metric_samples: Vec<Metric> = (0..metric_num_samples)
-                                .map(|_| testone(&name, hid, &args, &param, Some(&metric_name)))
-                                .filter_map(|result| result)
-                                .collect();
-                            if let Some(result) = average(metric_samples) {
-                                let mut pool = pool.lock().unwrap();
-                                pool.push((param, result));
-                            } else {
-                                eprintln!("[Warning!] No Metric Report detected!");
-                            }
-                        });
-                        handles.push_back(handle);
-                    } else {
-                        if opt.debug {
-                            eprintln!("No More Job");
+                Lookup::Miss => {}
+            }
+            let name = name.clone();
+            let args = args.clone();
+            let cache = cache.clone();
+            let metric_names = metric_names.clone();
+            let pruner = pruner.clone();
+            let optimizer = optimizer.clone();
+            let id = hid;
+            let unfinished = Unfinished {
+                trial: Some((key, param)),
+                cache,
+                optimizer,
+            };
+            pool.spawn(id, move || {
+                let mut metric_samples: Vec<Vec<Metric>> = vec![];
+                let mut status = Status::Completed;
+                for _ in 0..num_samples {
+                    let mut attempt = 0;
+                    let (s, metrics) = loop {
+                        let (s, metrics) = testone(
+                            &name,
+                            id,
+                            seed,
+                            progress,
+                            &args,
+                            &param_args,
+                            &metric_names,
+                            pruner.as_ref().as_ref(),
+                            trial_timeout,
+                        );
+                        if s != Status::Failed || attempt >= retry || signal::received().is_some() {
+                            break (s, metrics);
                         }
+                        // exponential backoff for transient failures
+                        let wait = 1u64 << attempt.min(6);
+                        attempt += 1;
+                        eprintln!(
+                            "\x1b[33m[Retry] HID={} ({}/{}) in {}s\x1b[0m",
+                            id, attempt, retry, wait
+                        );
+                        thread::sleep(Duration::from_secs(wait));
+                    };
+                    status = s;
+                    if status != Status::Completed {
                         break;
                     }
-                    while handles.len() >= opt.parallels() {
-                        let handle_top = handles.pop_front().unwrap();
-                        handle_top.join().unwrap();
-                    }
-                }
-                // Run Rest All
-                while let Some(handle) = handles.pop_front() {
-                    handle.join().unwrap()
+                    metric_samples.extend(metrics);
                 }
-
-                // Eliminate Top Seeds
+                let result = if status == Status::Completed {
+                    average_each(metric_samples)
+                } else {
+                    None
+                };
+                if status == Status::Pruned {
+                    eprintln!("\x1b[33m[Pruned] HID={}\x1b[0m", id);
+                } else if status == Status::Completed
+                    && !metric_names.is_empty()
+                    && result.is_none()
                 {
-                    let mut pool = pool.lock().unwrap();
-                    pool.sort_by_key(|item| Total(item.1.value));
-                    if obj == Objective::Maximize {
-                        pool.reverse();
-                    }
-                    pool.truncate(opt.optimize.np);
-                    if opt.debug || opt.debug {
-                        eprintln!("The {}-th Generation Top: {:?}", gen, pool[0]);
-                    }
+                    eprintln!("[Warning!] No Metric Report detected!");
+                }
+                let cache = unfinished.cache.clone();
+                let optimizer = unfinished.optimizer.clone();
+                let (key, param) = unfinished.finish();
+                let waiting = cache.lock().unwrap().finish(&key, result.clone());
+                let mut optimizer = optimizer.lock().unwrap();
+                for param in waiting {
+                    optimizer.observe(param, result.clone());
                 }
+                optimizer.observe(param, result);
+            });
+            hid += 1;
+            // Parallel Max to -j; a slot is refilled as soon as any trial finishes
+            if pool.is_full() {
+                pool.wait_any();
             }
+        } else if !pool.wait_any() {
+            // No running trials to propose more
+            if opt.debug {
+                eprintln!("No More Job");
+            }
+            break;
+        }
+    }
+    // Wait Rest All
+    pool.wait_all();
 
-            // Finish
-            let pool = pool.lock().unwrap();
-            println!(
-                "\x1b[31m{} {} = {} when {:?}\x1b[0m",
-                if obj == Objective::Maximize {
-                    "Max"
-                } else {
-                    "Min"
-                },
-                metric_name,
-                pool[0].1.value,
-                pool[0].0
-            );
+    // Finish
+    if !objectives.is_empty() {
+        let mut front = optimizer.lock().unwrap().best();
+        pareto::sort(&objectives[..1], &mut front);
+        if front.is_empty() {
+            eprintln!("[Warning!] No trial reported {}", metric_names.join(", "));
+        }
+        if front.len() > 1 {
+            println!("\x1b[31mPareto Front ({} trials):\x1b[0m", front.len());
+        }
+        for (param, metrics) in front {
+            let values: Vec<String> = objectives
+                .iter()
+                .zip(metrics.iter())
+                .map(|(obj, metric)| {
+                    format!(
+                        "{} {} = {}",
+                        if *obj == Objective::Maximize {
+                            "Max"
+                        } else {
+                            "Min"
+                        },
+                        metric.metric,
+                        metric.value
+                    )
+                })
+                .collect();
+            println!("\x1b[31m{} when {:?}\x1b[0m", values.join(", "), param);
         }
     }
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn testone(
     name: &String,
     id: usize,
-    args: &Vec<String>,
-    param: &Vec<(String, Value)>,
-    watching_metric: Option<&String>,
-) -> Option<Metric> {
-    let mut args = args.clone();
+    seed: u64,
+    progress: Option<usize>,
+    args: &[String],
+    param_args: &[String],
+    watching_metrics: &[String],
+    pruner: Option<&MedianPruner>,
+    timeout: Option<Duration>,
+) -> (Status, Option<Vec<Metric>>) {
+    let mut args = args.to_vec();
     args.push(format!("HID={}", id));
-    for (key, val) in param.iter() {
-        let s = match val {
-            Value::Val(x) => format!("{}={}", key, x),
-            Value::Int(x) => format!("{}={}", key, x),
-            Value::Float(x) => format!("{}={}", key, x),
-            _ => panic!("Cannot stringify"),
-        };
-        args.push(s);
-    }
-    let log = log_file_name(&name, id);
+    args.extend(param_args.iter().cloned());
+    let log = log_file_name(name, id);
     eprintln!(
         "\x1b[34mHake (NAME={}, ID={}, log=>{:?})\x1b[0m",
         &name, id, log
@@ -242,14 +426,34 @@ fn testone(
     let mut child = Command::new("make")
         .args(&args)
         .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // its own process group, to kill the whole tree of make
+        .process_group(0)
         .spawn()
         .expect("Something Error to Make");
-    listen(&mut child, &name, &log, &args, watching_metric)
+    signal::enter(child.id());
+    let mut header =
+        json!({"name": &name, "make_args": &args, "git_hash": git_hash(), "seed": seed});
+    if let Some(progress) = progress {
+        // to resume the optimizer from
+        header["progress"] = json!(progress);
+    }
+    let result = listen(
+        &mut child,
+        id,
+        &log,
+        header,
+        watching_metrics,
+        pruner,
+        timeout,
+    );
+    signal::leave(child.id());
+    result
 }
 
 fn git_hash() -> String {
     let result = Command::new("git")
-        .args(&["log", "--pretty=format:%H", "-1"])
+        .args(["log", "--pretty=format:%H", "-1"])
         .stdout(Stdio::piped())
         .output();
     match result {
@@ -258,17 +462,59 @@ fn git_hash() -> String {
     }
 }
 
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// lines buffered between the pipes of make and the log
+const CHANNEL_BOUND: usize = 64;
+
+/// Kill make and its children
+fn kill_group(child: &mut Child) {
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Forward lines of a pipe to the channel until EOF
+fn forward<R: Read + Send + 'static>(
+    pipe: R,
+    stream: Stream,
+    tx: SyncSender<(Stream, String)>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = vec![];
+        while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+            if n == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\n', '\r']).to_string();
+            if tx.send((stream, line)).is_err() {
+                break;
+            }
+            buf.clear();
+        }
+    })
+}
+
 fn listen(
     child: &mut Child,
-    name: &String,
+    id: usize,
     log: &String,
-    args: &Vec<String>,
-    watching_metric: Option<&String>,
-) -> Option<Metric> {
+    header: serde_json::Value,
+    watching_metrics: &[String],
+    pruner: Option<&MedianPruner>,
+    timeout: Option<Duration>,
+) -> (Status, Option<Vec<Metric>>) {
     use std::fs::{create_dir_all, OpenOptions};
     create_dir_all(".hake/log").unwrap();
     let mut log = OpenOptions::new()
-        .write(true)
         .create(true)
         .append(true)
         .open(log)
@@ -285,34 +531,120 @@ fn listen(
         }
     };
 
-    tee(
-        json!({"name": &name, "make_args": &args, "git_hash": git_hash()}).to_string(),
-        false,
-    );
+    tee(header.to_string(), false);
 
-    let mut last_metric = None;
-
-    if let Some(out) = child.stdout.as_mut() {
-        let reader = BufReader::new(out);
-        for line in reader.lines() {
-            match line {
-                Ok(line) => {
-                    if let Ok(metric) = serde_json::from_str::<Metric>(&line) {
-                        let is_watching = watching_metric.as_ref() == Some(&&metric.metric);
-                        if is_watching {
-                            last_metric = Some(metric);
-                        }
-                        tee(line, is_watching);
-                    } else {
-                        tee(line, false);
+    let mut last_metrics: Vec<Option<Metric>> = vec![None; watching_metrics.len()];
+
+    // stdout and stderr are read concurrently, so that neither pipe fills up;
+    // the channel is bounded, so a flooding make waits for the log to catch up
+    let (tx, rx) = mpsc::sync_channel(CHANNEL_BOUND);
+    if let Some(out) = child.stdout.take() {
+        forward(out, Stream::Stdout, tx.clone());
+    }
+    if let Some(err) = child.stderr.take() {
+        forward(err, Stream::Stderr, tx.clone());
+    }
+    drop(tx);
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    // the signal forwarded to make, and the end of the grace period
+    let mut interrupted: Option<(i32, Instant)> = None;
+    loop {
+        // checked on every line, since a chatty make never lets recv time out
+        let now = Instant::now();
+        match interrupted {
+            Some((_, end)) if now >= end || signal::forced() => break,
+            Some(_) => {}
+            None => {
+                if let Some(sig) = signal::received() {
+                    unsafe {
+                        libc::kill(-(child.id() as libc::pid_t), sig);
                     }
+                    interrupted = Some((sig, now + signal::grace()));
+                }
+            }
+        }
+        if interrupted.is_none() && deadline.is_some_and(|deadline| now >= deadline) {
+            kill_group(child);
+            let line = json!({
+                "status": Status::Timeout.name(),
+                "timeout": timeout.unwrap().as_secs(),
+            });
+            tee(line.to_string(), false);
+            eprintln!("\x1b[33m[Timeout] HID={} {}\x1b[0m", id, line);
+            return (Status::Timeout, None);
+        }
+        // wake up regularly to check signals
+        let mut until = now + POLL_INTERVAL;
+        if let Some(deadline) = deadline {
+            until = until.min(deadline);
+        }
+        let received = rx.recv_timeout(until.saturating_duration_since(Instant::now()));
+        let (stream, line) = match received {
+            Ok(received) => received,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+        };
+        if stream == Stream::Stderr {
+            tee(format!("[stderr] {}", line), false);
+        } else if let Ok(metric) = serde_json::from_str::<Metric>(&line) {
+            let watching = watching_metrics
+                .iter()
+                .position(|name| name == &metric.metric);
+            let is_watching = watching.is_some();
+            let prune = match (pruner, metric.step) {
+                (Some(pruner), Some(step)) if pruner.metric() == &metric.metric => {
+                    pruner.report(id, step, metric.value)
                 }
-                _ => {}
+                _ => false,
+            };
+            if let Some(k) = watching {
+                last_metrics[k] = Some(metric.clone());
+            }
+            tee(line, is_watching);
+            if prune {
+                kill_group(child);
+                tee(
+                    json!({"status": Status::Pruned.name(), "step": metric.step}).to_string(),
+                    false,
+                );
+                return (Status::Pruned, None);
             }
+        } else {
+            tee(line, false);
         }
     }
 
-    last_metric
+    if let Some((sig, _)) = interrupted {
+        // exited in the grace period, or killed
+        kill_group(child);
+        let line = json!({"status": Status::Interrupted.name(), "signal": sig});
+        tee(line.to_string(), false);
+        eprintln!("\x1b[33m[Interrupted] HID={}\x1b[0m", id);
+        return (Status::Interrupted, None);
+    }
+
+    let exit = child.wait();
+    let status = match &exit {
+        Ok(exit) if exit.success() => Status::Completed,
+        _ => Status::Failed,
+    };
+    let mut line = json!({ "status": status.name() });
+    if let Ok(exit) = exit {
+        if let Some(code) = exit.code() {
+            line["exit_code"] = json!(code);
+        }
+        if let Some(signal) = exit.signal() {
+            line["signal"] = json!(signal);
+        }
+    }
+    tee(line.to_string(), false);
+    if status == Status::Failed {
+        eprintln!("\x1b[33m[Failed] HID={} {}\x1b[0m", id, line);
+        return (Status::Failed, None);
+    }
+
+    (Status::Completed, last_metrics.into_iter().collect())
 }
 
 fn main() -> Result<(), String> {
@@ -320,5 +652,10 @@ fn main() -> Result<(), String> {
     if opt.debug {
         eprintln!("{:?}", &opt);
     }
-    make(&opt)
+    signal::install(Duration::from_secs(opt.grace_period));
+    make(&opt)?;
+    if let Some(sig) = signal::received() {
+        std::process::exit(128 + sig);
+    }
+    Ok(())
 }
@@ -1,57 +1,378 @@
 extern crate rand;
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{take_while, take_while1},
+    character::complete::{anychar, char, none_of, one_of},
+    combinator::{all_consuming, map, opt},
+    multi::many0,
+    sequence::{delimited, preceded, separated_pair, tuple},
+    IResult,
+};
+
+use crate::dist::Dist;
+use crate::expr::Expr;
+use crate::permutation::Permutation;
 
 pub type Param = Vec<(String, Value)>;
 
-#[derive(Debug, Clone)]
+/// KEY=VALUE arguments for make
+pub fn args(param: &[(String, Value)]) -> Vec<String> {
+    param
+        .iter()
+        .map(|(key, val)| format!("{}={}", key, val.show()))
+        .collect()
+}
+
+/// A key is active only when PARENT takes one of the values (`KEY[PARENT=v1,v2]=VALUE`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub parent: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Map {
     pub data: Param,
+    /// conditions every Param should satisfy
+    pub constraints: Vec<Expr>,
+    /// conditional keys
+    pub conditions: Vec<(String, Condition)>,
+    /// derived keys (`KEY:=EXPR`), passed to make but not searched
+    pub derived: Vec<(String, Expr)>,
 }
 
 impl Map {
     pub fn new() -> Self {
-        Map { data: Vec::new() }
+        Map {
+            data: Vec::new(),
+            constraints: Vec::new(),
+            conditions: Vec::new(),
+            derived: Vec::new(),
+        }
     }
-    pub fn parse_pair(pair: &String) -> Result<(String, Value), String> {
-        let f = pair.split('=').collect::<Vec<_>>();
-        if f.len() == 2 {
-            let key = f[0].to_string();
-            let value = Value::from(&f[1].to_string())?;
-            Ok((key, value))
+    /// KEY=VALUE, KEY~DIST or KEY[PARENT=v1,v2]=VALUE
+    ///
+    /// Split at the first `=` or `~` (outside of the condition).
+    /// In values, `'...'` is literal and `\x` escapes x (e.g. `DATA='v1..v2'`, `OPTS=a\,b`).
+    #[allow(dead_code)]
+    pub fn parse_entry(pair: &str) -> Result<(String, Option<Condition>, Value), String> {
+        fn key(input: &str) -> IResult<&str, &str> {
+            take_while1(|c| c != '=' && c != '~' && c != '[')(input)
+        }
+        fn condition(input: &str) -> IResult<&str, (&str, &str)> {
+            delimited(
+                char('['),
+                separated_pair(
+                    take_while1(|c| c != '=' && c != ']'),
+                    char('='),
+                    take_while(|c| c != ']'),
+                ),
+                char(']'),
+            )(input)
+        }
+        let (value, (key, cond, sep)) = tuple((key, opt(condition), one_of("=~")))(pair).map_err(
+            |_: nom::Err<nom::error::Error<&str>>| {
+                format!(
+                    "Parse Error: {:?} should be KEY=VALUE, KEY~DIST or KEY[PARENT=v1,v2]=VALUE",
+                    pair
+                )
+            },
+        )?;
+        let cond = match cond {
+            Some((parent, values)) => Some(Condition {
+                parent: parent.to_string(),
+                values: split(&lex(values)?, ",")
+                    .iter()
+                    .map(|xs| text(xs))
+                    .collect(),
+            }),
+            None => None,
+        };
+        let value = if sep == '~' {
+            Dist::parse(value).map(Value::Dist)
         } else {
-            Err(format!("Parse Error: {:?}", &pair))
+            Value::from(&value.to_string())
+        };
+        let value = value.map_err(|e| format!("{} (in {:?})", e, pair))?;
+        Ok((key.to_string(), cond, value))
+    }
+    /// KEY=VALUE (without conditions)
+    #[allow(dead_code)]
+    pub fn parse_pair(pair: &String) -> Result<(String, Value), String> {
+        match Map::parse_entry(pair)? {
+            (key, None, value) => Ok((key, value)),
+            _ => Err(format!("Condition is not allowed here: {:?}", pair)),
         }
     }
+    /// KEY=VALUE given to make, where VALUE is always literal
+    #[allow(dead_code)]
+    pub fn parse_literal(arg: &str) -> Option<(String, Value)> {
+        let (key, value) = arg.split_once('=')?;
+        Some((key.to_string(), Value::Val(value.to_string())))
+    }
     pub fn add(&mut self, key: String, val: Value) {
         self.data.push((key, val));
     }
+    /// Overwrite the value of KEY (or add it)
+    #[allow(dead_code)]
+    pub fn set(&mut self, key: String, val: Value) {
+        match self.position(&key) {
+            Some(i) => self.data[i].1 = val,
+            None => self.add(key, val),
+        }
+    }
+    #[allow(dead_code)]
+    pub fn add_constraint(&mut self, expr: &str) -> Result<(), String> {
+        let expr = Expr::parse(expr)?;
+        for key in expr.vars() {
+            if self.get(&key).is_none() {
+                return Err(format!("Unknown key in constraint: {}", key));
+            }
+        }
+        self.constraints.push(expr);
+        Ok(())
+    }
+    /// Make KEY active only under the condition on an earlier key
+    #[allow(dead_code)]
+    pub fn add_condition(&mut self, key: &str, cond: Condition) -> Result<(), String> {
+        let i = self.position(key).ok_or(format!("Unknown key: {}", key))?;
+        match self.position(&cond.parent) {
+            Some(j) if j < i => {}
+            _ => {
+                return Err(format!(
+                    "Parent `{}` of `{}` should be given before it",
+                    cond.parent, key
+                ))
+            }
+        }
+        self.conditions.retain(|(k, _)| k != key);
+        self.conditions.push((key.to_string(), cond));
+        Ok(())
+    }
+    /// KEY:=EXPR over the keys (and the derived keys before)
+    #[allow(dead_code)]
+    pub fn add_derived(&mut self, key: &str, expr: &str) -> Result<(), String> {
+        let expr = Expr::parse(expr)?;
+        let known = |map: &Map, k: &str| {
+            map.position(k).is_some() || map.derived.iter().any(|(d, _)| d == k)
+        };
+        if known(self, key) {
+            return Err(format!("Derived key `{}` is already defined", key));
+        }
+        for var in expr.vars() {
+            if !known(self, &var) {
+                return Err(format!("Unknown key in derived `{}`: {}", key, var));
+            }
+        }
+        self.derived.push((key.to_string(), expr));
+        Ok(())
+    }
+    /// Param followed by the derived keys
+    pub fn derive(&self, param: &Param) -> Result<Param, String> {
+        let mut param = param.clone();
+        for (key, expr) in self.derived.iter() {
+            let val = expr
+                .eval(&param)
+                .map_err(|e| format!("Cannot derive `{}`: {}", key, e))?;
+            param.push((key.clone(), val.to_value()));
+        }
+        Ok(param)
+    }
+    fn position(&self, key: &str) -> Option<usize> {
+        self.data.iter().position(|(k, _)| k == key)
+    }
+    /// Is the i-th key active in the param?
+    pub fn active(&self, param: &Param, i: usize) -> bool {
+        let key = &self.data[i].0;
+        match self.conditions.iter().find(|(k, _)| k == key) {
+            None => true,
+            Some((_, cond)) => match self.position(&cond.parent) {
+                Some(j) => self.active(param, j) && cond.values.contains(&param[j].1.show()),
+                None => false,
+            },
+        }
+    }
+    /// Inactive keys take their first values, so that equivalent params are identical
+    pub fn normalize(&self, mut param: Param) -> Param {
+        for i in 0..self.data.len() {
+            if !self.active(&param, i) {
+                param[i].1 = self.data[i].1.index(0);
+            }
+        }
+        param
+    }
+    /// KEY=VALUE of the active keys (identifies the param)
+    pub fn args(&self, param: &Param) -> Vec<String> {
+        let active: Param = (0..param.len())
+            .filter(|&i| self.active(param, i))
+            .map(|i| param[i].clone())
+            .collect();
+        args(&active)
+    }
+    /// KEY=VALUE arguments for make (the active keys and the derived keys)
+    pub fn make_args(&self, param: &Param) -> Result<Vec<String>, String> {
+        let full = self.derive(param)?;
+        let mut xs = self.args(param);
+        xs.extend(args(&full[param.len()..]));
+        Ok(xs)
+    }
+    /// Does the param satisfy all the constraints?
+    pub fn feasible(&self, param: &Param) -> bool {
+        self.constraints
+            .iter()
+            .all(|expr| expr.test(param).unwrap_or(false))
+    }
+    #[allow(dead_code)]
     pub fn iter(&self) -> MapIter {
+        self.iter_by(Permutation::Identity)
+    }
+    /// Enumerate in the order of the permutation of indices
+    ///
+    /// The grid should be indexable (`size` is not None).
+    pub fn iter_by(&self, perm: Permutation) -> MapIter {
         MapIter {
             idx: 0,
+            size: self.size().unwrap_or(u128::MAX),
             data: self.clone(),
+            perm,
         }
     }
-    pub fn index(&self, idx: usize) -> Param {
+    pub fn index(&self, idx: u128) -> Param {
         let mut ret = vec![];
         let mut i = idx;
         for (key, val) in self.data.iter() {
-            ret.push((key.clone(), val.index(i % val.len())));
-            i /= val.len();
+            let n = val.len() as u128;
+            ret.push((key.clone(), val.index((i % n) as usize)));
+            i /= n;
         }
         ret
     }
+    /// Num of the grid points to index (None if over u128)
+    pub fn size(&self) -> Option<u128> {
+        self.data
+            .iter()
+            .try_fold(1u128, |prod, (_, val)| prod.checked_mul(val.len() as u128))
+    }
+    /// Num of the grid points (saturating at usize::MAX)
     pub fn len(&self) -> usize {
-        let mut prod = 1;
-        for (_, val) in self.data.iter() {
-            prod *= val.len();
+        self.data
+            .iter()
+            .fold(1, |prod: usize, (_, val)| prod.saturating_mul(val.len()))
+    }
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Has continuous keys (which grid cannot enumerate)?
+    pub fn is_continuous(&self) -> bool {
+        self.data.iter().any(|(_, val)| val.is_continuous())
+    }
+    /// Replace each distribution with n quantiles
+    #[allow(dead_code)]
+    pub fn discretize(&mut self, n: usize) {
+        for (_, val) in self.data.iter_mut() {
+            if let Value::Dist(dist) = val {
+                let xs = (0..n)
+                    .map(|k| dist.quantile((k as f64 + 0.5) / n as f64).to_string())
+                    .collect();
+                *val = Value::Choice(xs);
+            }
         }
-        prod
     }
-    pub fn rand(&self) -> Param {
-        let mut rng = rand::thread_rng();
-        let range = Uniform::from(0..self.len());
-        let idx = range.sample(&mut rng);
-        self.index(idx)
+    /// Uniform sampling from the feasible points (by rejection)
+    ///
+    /// Continuous keys are drawn from their distributions.
+    pub fn rand<R: Rng>(&self, rng: &mut R) -> Param {
+        // each key independently, since the grid may be too large to index
+        let draw = |rng: &mut R| {
+            let param = self
+                .data
+                .iter()
+                .map(|(key, val)| match val {
+                    Value::Dist(dist) => (key.clone(), Value::Float(dist.sample(rng))),
+                    _ => (
+                        key.clone(),
+                        val.index(Uniform::from(0..val.len()).sample(rng)),
+                    ),
+                })
+                .collect();
+            self.normalize(param)
+        };
+        let mut param = draw(rng);
+        for _ in 0..10000 {
+            if self.feasible(&param) {
+                return param;
+            }
+            param = draw(rng);
+        }
+        eprintln!("[Warning!] No feasible point found for the constraints");
+        param
+    }
+    /// Num of keys to search (having more than one value)
+    #[allow(dead_code)]
+    pub fn dims(&self) -> usize {
+        self.data
+            .iter()
+            .filter(|(_, val)| val.len() > 1 || val.is_continuous())
+            .count()
+    }
+    /// Param at the point of the unit cube [0, 1)^dims
+    #[allow(dead_code)]
+    pub fn at(&self, point: &[f64]) -> Param {
+        let mut coords = point.iter();
+        let param = self
+            .data
+            .iter()
+            .map(|(key, val)| {
+                let n = val.len();
+                if let Value::Dist(dist) = val {
+                    let u = coords.next().copied().unwrap_or(0.5);
+                    (key.clone(), Value::Float(dist.quantile(u)))
+                } else if n > 1 {
+                    let u = coords.next().copied().unwrap_or(0.0);
+                    let k = std::cmp::min((u * n as f64) as usize, n - 1);
+                    (key.clone(), val.index(k))
+                } else {
+                    (key.clone(), val.index(0))
+                }
+            })
+            .collect();
+        self.normalize(param)
+    }
+    /// Param from KEY=VALUE arguments given to make (the last one wins)
+    ///
+    /// Missing keys are allowed only when they are inactive.
+    #[allow(dead_code)]
+    pub fn decode(&self, args: &[String]) -> Option<Param> {
+        use Value::*;
+        let mut missing = vec![];
+        let mut param = vec![];
+        for (i, (key, val)) in self.data.iter().enumerate() {
+            let prefix = format!("{}=", key);
+            let x = match args.iter().rev().find_map(|arg| arg.strip_prefix(&prefix)) {
+                Some(x) => x,
+                None => {
+                    missing.push(i);
+                    param.push((key.clone(), val.index(0)));
+                    continue;
+                }
+            };
+            let x = match val {
+                Val(_) | Int(_) | Float(_) => val.clone(),
+                Choice(_) => Val(x.to_string()),
+                IntRange(..) | IntLogRange(..) => Int(x.parse().ok()?),
+                FloatRange(..) | FloatLogRange(..) | Dist(_) => Float(x.parse().ok()?),
+            };
+            param.push((key.clone(), x));
+        }
+        if missing.iter().any(|&i| self.active(&param, i)) {
+            None
+        } else {
+            Some(param)
+        }
     }
     #[allow(dead_code)]
     pub fn get(&self, key: &String) -> Option<&Value> {
@@ -65,20 +386,24 @@ impl Map {
 }
 
 pub struct MapIter {
-    idx: usize,
+    idx: u128,
+    size: u128,
     data: Map,
+    perm: Permutation,
 }
 
 impl Iterator for MapIter {
     type Item = Vec<(String, Value)>;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.idx >= self.data.len() {
-            None
-        } else {
-            let ret = self.data.index(self.idx);
+        while self.idx < self.size {
+            let ret = self.data.index(self.perm.get(self.idx));
             self.idx += 1;
-            Some(ret)
+            // inactive keys vary only in the redundant points
+            if self.data.normalize(ret.clone()) == ret && self.data.feasible(&ret) {
+                return Some(ret);
+            }
         }
+        None
     }
 }
 
@@ -90,6 +415,58 @@ pub enum Value {
     Choice(Vec<String>),
     IntRange(i64, i64, i64), // begin, end, skip
     FloatRange(f64, f64, f64),
+    IntLogRange(i64, i64, f64), // begin, end, ratio
+    FloatLogRange(f64, f64, f64),
+    /// continuous (`KEY~DIST`)
+    Dist(Dist),
+}
+
+/// A char of a value, and whether it is literal (quoted or escaped)
+type Lexeme = (char, bool);
+
+fn lex(input: &str) -> Result<Vec<Lexeme>, String> {
+    fn quoted(input: &str) -> IResult<&str, Vec<Lexeme>> {
+        map(
+            delimited(char('\''), take_while(|c| c != '\''), char('\'')),
+            |s: &str| s.chars().map(|c| (c, true)).collect(),
+        )(input)
+    }
+    fn escaped(input: &str) -> IResult<&str, Vec<Lexeme>> {
+        map(preceded(char('\\'), anychar), |c| vec![(c, true)])(input)
+    }
+    fn plain(input: &str) -> IResult<&str, Vec<Lexeme>> {
+        map(none_of("'\\"), |c| vec![(c, false)])(input)
+    }
+    match all_consuming(many0(alt((quoted, escaped, plain))))(input) {
+        Ok((_, xs)) => Ok(xs.concat()),
+        Err(_) => Err(format!(
+            "Unterminated quote or trailing backslash: {:?}",
+            input
+        )),
+    }
+}
+
+/// Split at the separator made of non-literal chars
+fn split(xs: &[Lexeme], sep: &str) -> Vec<Vec<Lexeme>> {
+    let sep: Vec<Lexeme> = sep.chars().map(|c| (c, false)).collect();
+    let mut fields = vec![];
+    let mut field = vec![];
+    let mut i = 0;
+    while i < xs.len() {
+        if xs[i..].starts_with(&sep) {
+            fields.push(std::mem::take(&mut field));
+            i += sep.len();
+        } else {
+            field.push(xs[i]);
+            i += 1;
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn text(xs: &[Lexeme]) -> String {
+    xs.iter().map(|(c, _)| c).collect()
 }
 
 fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
@@ -100,7 +477,30 @@ fn parse_number<T: std::str::FromStr>(s: &str) -> Result<T, String> {
     }
 }
 
+/// Num of geometric steps from begin to end
+fn log_len(begin: f64, end: f64, ratio: f64) -> usize {
+    if begin == end {
+        1
+    } else {
+        ((end / begin).ln() / ratio.ln() + 1.0 + 1e-9).floor() as usize
+    }
+}
+
 impl Value {
+    /// String for make (only for single values)
+    pub fn show(&self) -> String {
+        match self {
+            Value::Val(x) => x.clone(),
+            Value::Int(x) => x.to_string(),
+            Value::Float(x) => x.to_string(),
+            _ => panic!("Cannot stringify"),
+        }
+    }
+
+    pub fn is_continuous(&self) -> bool {
+        matches!(self, Value::Dist(_))
+    }
+
     pub fn len(&self) -> usize {
         use Value::*;
         match self {
@@ -108,9 +508,18 @@ impl Value {
             Choice(xs) => xs.len(),
             IntRange(begin, end, skip) => ((end - begin) / skip + 1) as usize,
             FloatRange(begin, end, skip) => ((end - begin) / skip + 1.0).floor() as usize,
+            IntLogRange(begin, end, ratio) => log_len(*begin as f64, *end as f64, *ratio),
+            FloatLogRange(begin, end, ratio) => log_len(*begin, *end, *ratio),
+            // a single slot in the grid; see `Map::rand`
+            Dist(_) => 1,
         }
     }
 
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn index(&self, i: usize) -> Self {
         use Value::*;
         match &self {
@@ -118,16 +527,57 @@ impl Value {
             Choice(xs) => Val(xs[i].clone()),
             IntRange(begin, _, skip) => Int(begin + skip * i as i64),
             FloatRange(begin, _, skip) => Float(begin + skip * i as f64),
+            IntLogRange(begin, _, ratio) => {
+                Int((*begin as f64 * ratio.powi(i as i32)).round() as i64)
+            }
+            FloatLogRange(begin, _, ratio) => Float(begin * ratio.powi(i as i32)),
+            // the median as the representative
+            Dist(dist) => Float(dist.quantile(0.5)),
         }
     }
 
+    /// Value from the syntax (quoted or escaped chars are never operators)
     pub fn from(val: &String) -> Result<Self, String> {
-        if val.contains("...") {
-            let f = val.split("...").collect::<Vec<_>>();
+        let xs = lex(val)?;
+        let fields =
+            |sep: &str| -> Vec<String> { split(&xs, sep).iter().map(|f| text(f)).collect() };
+        if fields("~~").len() > 1 {
+            let f = fields("~~");
+            if f.len() != 2 && f.len() != 3 {
+                return Err("Log-Range ~~ should have 2 or 3 fields. See document.".to_string());
+            }
+            let is_int = f.iter().all(|x| x.parse::<i64>().is_ok());
+            let nums = f
+                .iter()
+                .map(|x| parse_number::<f64>(x))
+                .collect::<Result<Vec<_>, _>>()?;
+            if nums.iter().any(|&x| x <= 0.0) {
+                return Err(format!("Log-Range ~~ should be positive: {:?}", &val));
+            }
+            let begin = nums[0];
+            let end = nums[nums.len() - 1];
+            let ratio = if nums.len() == 2 {
+                (end / begin).powf(0.1)
+            } else {
+                nums[1] / begin
+            };
+            if !((ratio > 1.0 && begin <= end) || (ratio < 1.0 && begin >= end) || begin == end) {
+                return Err(format!(
+                    "Log-Range ~~ has strange second value...? {:?}",
+                    &val
+                ));
+            }
+            if is_int {
+                Ok(Value::IntLogRange(begin as i64, end as i64, ratio))
+            } else {
+                Ok(Value::FloatLogRange(begin, end, ratio))
+            }
+        } else if fields("...").len() > 1 {
+            let f = fields("...");
             match f.len() {
                 2 => {
-                    let begin = parse_number::<f64>(f[0])?;
-                    let end = parse_number::<f64>(f[1])?;
+                    let begin = parse_number::<f64>(&f[0])?;
+                    let end = parse_number::<f64>(&f[1])?;
                     let skip = (end - begin) / 10.0;
                     if begin <= end {
                         Ok(Value::FloatRange(begin, end, skip))
@@ -139,20 +589,20 @@ impl Value {
                     }
                 }
                 3 => {
-                    let begin = parse_number::<f64>(f[0])?;
-                    let second = parse_number::<f64>(f[1])?;
-                    let end = parse_number::<f64>(f[2])?;
+                    let begin = parse_number::<f64>(&f[0])?;
+                    let second = parse_number::<f64>(&f[1])?;
+                    let end = parse_number::<f64>(&f[2])?;
                     let skip = second - begin;
                     Ok(Value::FloatRange(begin, end, skip))
                 }
                 _ => Err("Float-Range ... should have 2 or 3 fields. See document.".to_string()),
             }
-        } else if val.contains("..") {
-            let f = val.split("..").collect::<Vec<_>>();
+        } else if fields("..").len() > 1 {
+            let f = fields("..");
             match f.len() {
                 2 => {
-                    let begin = parse_number::<i64>(f[0])?;
-                    let end = parse_number::<i64>(f[1])?;
+                    let begin = parse_number::<i64>(&f[0])?;
+                    let end = parse_number::<i64>(&f[1])?;
                     if begin <= end {
                         Ok(Value::IntRange(begin, end, 1))
                     } else {
@@ -160,9 +610,9 @@ impl Value {
                     }
                 }
                 3 => {
-                    let begin = parse_number::<i64>(f[0])?;
-                    let second = parse_number::<i64>(f[1])?;
-                    let end = parse_number::<i64>(f[2])?;
+                    let begin = parse_number::<i64>(&f[0])?;
+                    let second = parse_number::<i64>(&f[1])?;
+                    let end = parse_number::<i64>(&f[2])?;
                     let skip = second - begin;
                     if (skip > 0 && begin <= end) || (skip < 0 && begin >= end) {
                         Ok(Value::IntRange(begin, end, skip))
@@ -175,12 +625,10 @@ impl Value {
                 }
                 _ => Err("Int-Range .. should have 2 or 3 fields. See document.".to_string()),
             }
-        } else if val.contains(",") {
-            Ok(Value::Choice(
-                val.split(',').map(|s| s.to_string()).collect(),
-            ))
+        } else if fields(",").len() > 1 {
+            Ok(Value::Choice(fields(",")))
         } else {
-            Ok(Value::Val(val.clone()))
+            Ok(Value::Val(text(&xs)))
         }
     }
 }
@@ -224,6 +672,16 @@ mod tests {
             Value::from(&String::from("0...-1...-5")),
             Ok(Value::FloatRange(0.0, -5.0, -1.0))
         );
+        assert_eq!(
+            Value::from(&String::from("1~~10~~1000")),
+            Ok(Value::IntLogRange(1, 1000, 10.0))
+        );
+        assert_eq!(
+            Value::from(&String::from("0.001~~0.01~~1")),
+            Ok(Value::FloatLogRange(0.001, 1.0, 10.0))
+        );
+        assert!(Value::from(&String::from("0~~1")).is_err());
+        assert!(Value::from(&String::from("1~~0.1~~10")).is_err());
     }
 
     #[test]
@@ -235,6 +693,18 @@ mod tests {
         assert_eq!(Value::IntRange(0, 10, 1).len(), 11);
         assert_eq!(Value::FloatRange(0.0, 10.0, 1.0).len(), 11);
         assert_eq!(Value::FloatRange(0.0, 10.0, 2.0).len(), 6);
+        assert_eq!(Value::IntLogRange(1, 1024, 2.0).len(), 11);
+        assert_eq!(Value::from(&String::from("1e-5~~1e-1")).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn value_index_log() {
+        let lr = Value::from(&String::from("1e-5~~1e-1")).unwrap();
+        match lr.index(5) {
+            Value::Float(x) => assert!((x - 1e-3).abs() < 1e-12),
+            _ => panic!(),
+        }
+        assert_eq!(Value::IntLogRange(1, 1024, 2.0).index(10), Value::Int(1024));
     }
 
     #[test]
@@ -255,4 +725,97 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn map_conditional() {
+        let mut map = Map::new();
+        for pair in ["OPT=sgd,adam", "MOMENTUM[OPT=sgd]=0..2"] {
+            let (key, cond, val) = Map::parse_entry(pair).unwrap();
+            map.add(key.clone(), val);
+            if let Some(cond) = cond {
+                map.add_condition(&key, cond).unwrap();
+            }
+        }
+        let params: Vec<Param> = map.iter().collect();
+        assert_eq!(params.len(), 4);
+        let args: Vec<Vec<String>> = params.iter().map(|p| map.args(p)).collect();
+        assert!(args.contains(&vec![String::from("OPT=adam")]));
+        assert!(args.contains(&vec![String::from("OPT=sgd"), String::from("MOMENTUM=2")]));
+        assert_eq!(
+            map.decode(&[String::from("OPT=adam")]),
+            Some(params[1].clone())
+        );
+        assert_eq!(map.decode(&[String::from("OPT=sgd")]), None);
+        assert!(Map::new()
+            .add_condition("X", map.conditions[0].1.clone())
+            .is_err());
+    }
+
+    #[test]
+    fn map_rand_seeded() {
+        use rand::SeedableRng;
+        let mut map = Map::new();
+        map.add(String::from("X"), Value::IntRange(0, 100, 1));
+        map.add(String::from("Y"), Value::FloatRange(0.0, 1.0, 0.1));
+        let draw = |seed: u64| -> Vec<Param> {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            (0..10).map(|_| map.rand(&mut rng)).collect()
+        };
+        assert_eq!(draw(1), draw(1));
+        assert_ne!(draw(1), draw(2));
+    }
+
+    #[test]
+    fn map_parse_quoted() {
+        let val = |s: &str| Map::parse_pair(&String::from(s)).map(|(_, v)| v);
+        assert_eq!(
+            val("DATA='path/v1..v2'"),
+            Ok(Value::Val(String::from("path/v1..v2")))
+        );
+        assert_eq!(val("OPTS=a=b"), Ok(Value::Val(String::from("a=b"))));
+        assert_eq!(val("VER=1.2'...'"), Ok(Value::Val(String::from("1.2..."))));
+        assert_eq!(
+            val("X=a\\,b,'c,d'"),
+            Ok(Value::Choice(vec![
+                String::from("a,b"),
+                String::from("c,d")
+            ]))
+        );
+        assert!(val("X='unterminated").is_err());
+        assert!(val("=1").is_err());
+        assert!(val("X[P=a]=1").is_err());
+        assert_eq!(
+            Map::parse_entry("M[OPT=sgd,'a,b']=1..2"),
+            Ok((
+                String::from("M"),
+                Some(Condition {
+                    parent: String::from("OPT"),
+                    values: vec![String::from("sgd"), String::from("a,b")],
+                }),
+                Value::IntRange(1, 2, 1)
+            ))
+        );
+    }
+
+    #[test]
+    fn map_derived() {
+        let mut map = Map::new();
+        map.add(String::from("HIDDEN"), Value::IntRange(64, 128, 64));
+        map.add_derived("HIDDEN2", "HIDDEN * 2").unwrap();
+        map.add_derived("HALF", "HIDDEN2 / 4").unwrap();
+        assert!(map.add_derived("X", "NONE + 1").is_err());
+        assert!(map.add_derived("HIDDEN", "1").is_err());
+        assert_eq!(map.dims(), 1);
+        assert_eq!(
+            map.make_args(&map.index(1)).unwrap(),
+            vec!["HIDDEN=128", "HIDDEN2=256", "HALF=64"]
+        );
+        assert_eq!(map.args(&map.index(1)), vec!["HIDDEN=128"]);
+
+        let mut map = Map::new();
+        map.add(String::from("X"), Value::IntRange(0, 1, 1));
+        map.add_derived("W", "10 / X").unwrap();
+        assert!(map.make_args(&map.index(0)).is_err());
+        assert_eq!(map.make_args(&map.index(1)).unwrap(), vec!["X=1", "W=10"]);
+    }
 }
@@ -4,6 +4,9 @@ use serde::Deserialize;
 pub struct Metric {
     pub metric: String,
     pub value: f64,
+    /// for intermediate reports (e.g. epoch)
+    #[serde(default)]
+    pub step: Option<u64>,
 }
 
 pub fn average(ms: Vec<Metric>) -> Option<Metric> {
@@ -15,6 +18,15 @@ pub fn average(ms: Vec<Metric>) -> Option<Metric> {
         Some(Metric {
             metric: name,
             value: avg,
+            step: None,
         })
     }
 }
+
+/// Average of each metric over samples
+pub fn average_each(samples: Vec<Vec<Metric>>) -> Option<Vec<Metric>> {
+    let n = samples.first()?.len();
+    (0..n)
+        .map(|k| average(samples.iter().map(|ms| ms[k].clone()).collect()))
+        .collect()
+}
@@ -1,15 +1,15 @@
 extern crate rand;
 
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
 use std::fs::{create_dir_all, File};
 use std::path::Path;
 
-pub fn gen() -> String {
-    let mut rng = rand::thread_rng();
+pub fn gen<R: Rng>(rng: &mut R) -> String {
     let adj = Uniform::from(0..ADJ.len());
     let noun = Uniform::from(0..NOUN.len());
-    let i = adj.sample(&mut rng);
-    let j = noun.sample(&mut rng);
+    let i = adj.sample(rng);
+    let j = noun.sample(rng);
     format!("{}_{}", ADJ[i], NOUN[j])
 }
 
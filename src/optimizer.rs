@@ -0,0 +1,95 @@
+/// Search Strategies
+use std::str::FromStr;
+
+use crate::map::Param;
+use crate::metric::Metric;
+use crate::options::Objective;
+use crate::pareto::dominates;
+
+/// A search strategy driven by `make()`.
+///
+/// `propose` may return None while it is waiting for running trials
+/// (e.g. the end of a generation); when it returns None and no trial is running,
+/// the search is finished.
+/// Results are the metrics of each objective in order.
+pub trait Optimizer: Send {
+    fn propose(&mut self) -> Option<Param>;
+    fn observe(&mut self, param: Param, result: Option<Vec<Metric>>);
+    /// Feed a trial done in the previous run (--resume)
+    fn restore(&mut self, param: Param, result: Option<Vec<Metric>>);
+    /// How far the search has gone (e.g. the generation), logged with each trial
+    fn progress(&self) -> Option<usize> {
+        None
+    }
+    /// Continue from the last progress logged in the previous run (--resume);
+    /// trials without logs (cache hits, failures before make) still count
+    fn resume(&mut self, _progress: usize) {}
+    /// The best trials (the Pareto front for multiple objectives)
+    fn best(&self) -> Vec<(Param, Vec<Metric>)>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptimizerKind {
+    Grid,
+    Random,
+    DE,
+    Tpe,
+    Hyperband,
+}
+
+impl OptimizerKind {
+    pub const NAMES: &'static [&'static str] = &["grid", "random", "de", "tpe", "hyperband"];
+}
+
+impl FromStr for OptimizerKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(OptimizerKind::Grid),
+            "random" => Ok(OptimizerKind::Random),
+            "de" => Ok(OptimizerKind::DE),
+            "tpe" => Ok(OptimizerKind::Tpe),
+            "hyperband" => Ok(OptimizerKind::Hyperband),
+            _ => Err(format!("Unknown optimizer: {:?}", s)),
+        }
+    }
+}
+
+/// The best trials so far (only when there are objectives)
+pub struct Best {
+    objectives: Vec<Objective>,
+    front: Vec<(Param, Vec<Metric>)>,
+}
+
+impl Best {
+    pub fn new(objectives: &[Objective]) -> Self {
+        Best {
+            objectives: objectives.to_vec(),
+            front: vec![],
+        }
+    }
+
+    pub fn update(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        if self.objectives.is_empty() {
+            return;
+        }
+        if let Some(metrics) = result {
+            let objs = &self.objectives;
+            let known = self.front.iter().any(|(p, _)| p == &param);
+            if known
+                || self
+                    .front
+                    .iter()
+                    .any(|(_, ms)| dominates(objs, ms, &metrics))
+            {
+                return;
+            }
+            self.front.retain(|(_, ms)| !dominates(objs, &metrics, ms));
+            self.front.push((param, metrics));
+        }
+    }
+
+    pub fn get(&self) -> Vec<(Param, Vec<Metric>)> {
+        self.front.clone()
+    }
+}
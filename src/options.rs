@@ -5,6 +5,11 @@ use structopt::StructOpt;
 
 use crate::map::*;
 use crate::name;
+use crate::optimizer::OptimizerKind;
+use crate::permutation::Order;
+use crate::qmc::Init;
+use crate::space;
+use crate::util;
 use nom::{
     branch::alt, bytes::complete::tag, character::complete::digit1, combinator::map,
     sequence::terminated, IResult,
@@ -27,11 +32,49 @@ pub struct Options {
     #[structopt(long, help = "Experiment Name")]
     pub name: Option<String>,
 
-    #[structopt(long, value_name = "metric", help = "Metric to Maximize", conflicts_with_all(&["min"]))]
-    pub max: Option<String>,
+    #[structopt(
+        long,
+        value_name = "name",
+        conflicts_with = "name",
+        help = "Resume the Experiment from its logs (with the same mappings)"
+    )]
+    pub resume: Option<String>,
+
+    #[structopt(
+        long,
+        value_name = "metric",
+        number_of_values = 1,
+        help = "Metric to Maximize (repeatable)"
+    )]
+    pub max: Vec<String>,
+
+    #[structopt(
+        long,
+        value_name = "metric",
+        number_of_values = 1,
+        help = "Metric to Minimize (repeatable)"
+    )]
+    pub min: Vec<String>,
+
+    #[structopt(
+        long,
+        possible_values = OptimizerKind::NAMES,
+        help = "Search Strategy (default: grid without metric, de with --max/--min)"
+    )]
+    pub optimizer: Option<OptimizerKind>,
 
-    #[structopt(long, value_name = "metric", help = "Metric to Minimize")]
-    pub min: Option<String>,
+    #[structopt(
+        long,
+        help = "Prune trials worse than the median at the same step (by the first of --max/--min given)"
+    )]
+    pub prune: bool,
+
+    #[structopt(
+        long,
+        default_value = "5",
+        help = "Num of other trials at the step needed before pruning"
+    )]
+    pub prune_startup: usize,
 
     #[structopt(
         long,
@@ -44,9 +87,49 @@ pub struct Options {
     #[structopt(help = "Target in H(M)akefile")]
     pub target: Option<String>,
 
-    #[structopt(name = "mapping", help = "KEY=VALUE or KEY=RANGE")]
+    #[structopt(
+        name = "mapping",
+        help = "KEY=VALUE, KEY=RANGE, KEY~DIST or KEY:=EXPR (KEY[PARENT=v1,v2]=... only when PARENT is v1 or v2)"
+    )]
     pub map: Vec<String>,
 
+    #[structopt(
+        long,
+        value_name = "N",
+        help = "Replace each distribution (KEY~DIST) with its N quantiles (e.g. for grid)"
+    )]
+    pub discretize: Option<usize>,
+
+    #[structopt(
+        long,
+        value_name = "file",
+        help = "Search Space in JSON (the mappings in the command line take precedence)"
+    )]
+    pub space: Option<String>,
+
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Condition on params, skipping the others (e.g. 'DIM % HEADS == 0') (repeatable)"
+    )]
+    pub constraint: Vec<String>,
+
+    #[structopt(
+        long,
+        help = "Reuse the results in .hake/log of the same makefile, targets, params and git hash"
+    )]
+    pub reuse_results: bool,
+
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Retry a failed trial (make exits with non-zero) up to N times, waiting 1s, 2s, 4s, ... in between"
+    )]
+    pub retry: usize,
+
+    #[structopt(long, help = "Random Seed for reproducible searches (default: random)")]
+    pub seed: Option<u64>,
+
     #[structopt(flatten)]
     pub optimize: OptimizeOptions,
 
@@ -58,6 +141,26 @@ pub struct Options {
         default_value = "0"
     )]
     pub timeout: u64,
+
+    #[structopt(
+        long,
+        parse(from_str = parse_timeout),
+        help = "Kill a trial running longer than this (0 for no timeout) (e.g. --trial-timeout 30m)",
+        default_value = "0"
+    )]
+    pub trial_timeout: u64,
+
+    #[structopt(
+        long,
+        parse(from_str = parse_timeout),
+        help = "On Ctrl-C (or SIGTERM, SIGHUP), time for the running trials to exit before killed (e.g. --grace-period 1m)",
+        default_value = "10"
+    )]
+    pub grace_period: u64,
+
+    /// --max and --min in the order of the command line
+    #[structopt(skip)]
+    objectives: Vec<(Objective, String)>,
 }
 
 #[derive(Debug, StructOpt)]
@@ -93,11 +196,77 @@ pub struct OptimizeOptions {
         help = "[Optimize] Num of Loop"
     )]
     pub num_loop: usize,
+
+    #[structopt(
+        long,
+        help = "[Optimize] Steady-state DE; each finished trial competes with its target at once, keeping all -j busy (for de)"
+    )]
+    pub steady_state: bool,
+
+    #[structopt(
+        long,
+        possible_values = Order::NAMES,
+        default_value = "lexicographic",
+        help = "[Optimize] Enumeration Order; lexicographic is the first key varying fastest (for grid)"
+    )]
+    pub order: Order,
+
+    #[structopt(long, help = "[Optimize] Max Num of Points to enumerate (for grid)")]
+    pub limit: Option<usize>,
+
+    #[structopt(
+        long,
+        possible_values = Init::NAMES,
+        default_value = "random",
+        help = "[Optimize] Initial Sampling (for de, random)"
+    )]
+    pub init: Init,
+
+    #[structopt(short = "T", long, help = "[Optimize] Num of Trials (for random, tpe)")]
+    pub trials: Option<usize>,
+
+    #[structopt(
+        long,
+        default_value = "10",
+        help = "[Optimize] Num of Random Trials before modeling (for tpe)"
+    )]
+    pub startup: usize,
+
+    #[structopt(
+        long,
+        value_name = "key",
+        help = "[Optimize] Key as the resource budget, given as a range (for hyperband)"
+    )]
+    pub budget: Option<String>,
+
+    #[structopt(
+        long,
+        default_value = "3",
+        help = "[Optimize] Reduction Factor of Successive Halving (for hyperband)"
+    )]
+    pub eta: usize,
 }
 
 impl Options {
     pub fn from() -> Self {
-        Options::from_args()
+        let matches = Options::clap().get_matches();
+        let mut opt = Options::from_clap(&matches);
+        let mut objectives = vec![];
+        for (name, obj, values) in [
+            ("max", Objective::Maximize, &opt.max),
+            ("min", Objective::Minimize, &opt.min),
+        ] {
+            let indices = matches.indices_of(name).into_iter().flatten();
+            for (i, value) in indices.zip(values.iter()) {
+                objectives.push((i, obj, value.clone()));
+            }
+        }
+        objectives.sort_by_key(|(i, _, _)| *i);
+        opt.objectives = objectives
+            .into_iter()
+            .map(|(_, obj, name)| (obj, name))
+            .collect();
+        opt
     }
 
     /// -f or `Hakefile` or `Makefile`
@@ -115,54 +284,112 @@ impl Options {
                     return Ok(f.to_string());
                 }
             }
-            Err(format!("Not found Hakefile nor Makefile"))
+            Err("Not found Hakefile nor Makefile".to_string())
         }
     }
 
-    /// --name or auto-generated name
-    pub fn name(&self) -> Result<String, String> {
-        if let Some(name) = self.name.clone() {
+    /// --resume, --name or auto-generated name
+    pub fn name(&self, seed: u64) -> Result<String, String> {
+        if let Some(name) = self.resume.clone() {
+            if name::exists(&name) {
+                Ok(name)
+            } else {
+                Err(format!("No such Experiment to resume: {}", name))
+            }
+        } else if let Some(name) = self.name.clone() {
             if name::exists(&name) {
                 Err(format!("Name Already Exists: {}", name))
             } else {
                 Ok(name)
             }
         } else {
-            let mut name = name::gen();
+            let mut rng = util::seeded(seed);
+            let mut name = name::gen(&mut rng);
             for _ in 0..1000 {
                 if name::exists(&name) {
-                    name = name::gen();
+                    name = name::gen(&mut rng);
                 } else {
                     break;
                 }
             }
             if name::exists(&name) {
-                Err(format!("Name exhausted!? Please consider to clean."))
+                Err("Name exhausted!? Please consider to clean.".to_string())
             } else {
                 Ok(name)
             }
         }
     }
 
-    pub fn target_map(&self) -> (Vec<String>, Map) {
+    pub fn target_map(&self) -> Result<(Vec<String>, Map), String> {
         let mut target = vec![];
-        let mut map = Map::new();
+        let (mut map, mut constraints, mut derived) = match &self.space {
+            Some(path) => {
+                let space = space::load(path)?;
+                (space.map, space.constraints, space.derived)
+            }
+            None => (Map::new(), vec![], vec![]),
+        };
         let args: Vec<String> = self.target.iter().chain(self.map.iter()).cloned().collect();
         for arg in args {
-            if let Ok((key, val)) = Map::parse_pair(&arg) {
-                map.add(key, val);
+            if let Some((key, expr)) = arg.split_once(":=").filter(|(key, _)| !key.contains('=')) {
+                // overrides the one in the space file
+                derived.retain(|(k, _)| k != key);
+                derived.push((key.to_string(), expr.to_string()));
+            // make takes any argument with `=` as a variable
+            } else if arg.contains('=') || arg.contains('~') {
+                let (key, cond, val) = Map::parse_entry(&arg)?;
+                derived.retain(|(k, _)| k != &key);
+                map.set(key.clone(), val);
+                if let Some(cond) = cond {
+                    map.add_condition(&key, cond)?;
+                }
             } else {
                 target.push(arg.clone());
             }
         }
-        (target, map)
+        for (key, expr) in derived {
+            map.add_derived(&key, &expr)?;
+        }
+        if let Some(n) = self.discretize {
+            map.discretize(n);
+        }
+        constraints.extend(self.constraint.iter().cloned());
+        for expr in constraints.iter() {
+            map.add_constraint(expr)?;
+        }
+        Ok((target, map))
+    }
+
+    /// --max and --min in the order given
+    pub fn objectives(&self) -> Vec<(Objective, String)> {
+        self.objectives.clone()
     }
 
-    pub fn metric(&self) -> Option<(Objective, String)> {
-        match (self.max.clone(), self.min.clone()) {
-            (Some(name), None) => Some((Objective::Maximize, name)),
-            (None, Some(name)) => Some((Objective::Minimize, name)),
-            _ => None,
+    /// --optimizer or chosen by --max/--min
+    pub fn optimizer(&self) -> Result<OptimizerKind, String> {
+        let has_metric = !self.objectives().is_empty();
+        match (self.optimizer, has_metric) {
+            (Some(OptimizerKind::DE), false) => {
+                Err("Optimizer `de` needs a metric (--max or --min)".to_string())
+            }
+            (Some(OptimizerKind::Tpe), false) => {
+                Err("Optimizer `tpe` needs a metric (--max or --min)".to_string())
+            }
+            (Some(OptimizerKind::Hyperband), false) => {
+                Err("Optimizer `hyperband` needs a metric (--max or --min)".to_string())
+            }
+            (Some(OptimizerKind::Hyperband), _) if self.optimize.budget.is_none() => {
+                Err("Optimizer `hyperband` needs --budget".to_string())
+            }
+            (Some(OptimizerKind::Random), _) if self.optimize.trials.is_none() => {
+                Err("Optimizer `random` needs --trials".to_string())
+            }
+            (Some(OptimizerKind::Tpe), _) if self.optimize.trials.is_none() => {
+                Err("Optimizer `tpe` needs --trials".to_string())
+            }
+            (Some(kind), _) => Ok(kind),
+            (None, false) => Ok(OptimizerKind::Grid),
+            (None, true) => Ok(OptimizerKind::DE),
         }
     }
 
@@ -0,0 +1,138 @@
+/// Multi-Objective Utilities (non-dominated sorting)
+use std::cmp::Ordering;
+
+use crate::map::Param;
+use crate::metric::Metric;
+use crate::options::Objective;
+
+/// Does `a` dominate `b`?
+pub fn dominates(objs: &[Objective], a: &[Metric], b: &[Metric]) -> bool {
+    let mut strict = false;
+    for (k, obj) in objs.iter().enumerate() {
+        match compare(*obj, &a[k], &b[k]) {
+            Ordering::Less => return false,
+            Ordering::Greater => strict = true,
+            Ordering::Equal => {}
+        }
+    }
+    strict
+}
+
+/// Greater is better
+fn compare(obj: Objective, a: &Metric, b: &Metric) -> Ordering {
+    let ord = a.value.partial_cmp(&b.value).unwrap_or(Ordering::Equal);
+    match obj {
+        Objective::Maximize => ord,
+        Objective::Minimize => ord.reverse(),
+    }
+}
+
+/// Rank of each point (0 for the Pareto front)
+pub fn ranks(objs: &[Objective], points: &[&[Metric]]) -> Vec<usize> {
+    let n = points.len();
+    let mut rank = vec![usize::MAX; n];
+    let mut r = 0;
+    while rank.contains(&usize::MAX) {
+        let front: Vec<usize> = (0..n)
+            .filter(|&i| rank[i] == usize::MAX)
+            .filter(|&i| {
+                !(0..n).any(|j| rank[j] == usize::MAX && dominates(objs, points[j], points[i]))
+            })
+            .collect();
+        for i in front {
+            rank[i] = r;
+        }
+        r += 1;
+    }
+    rank
+}
+
+/// Crowding distance of each point within its own rank
+fn crowding(objs: &[Objective], points: &[&[Metric]], rank: &[usize]) -> Vec<f64> {
+    let n = points.len();
+    let mut dist = vec![0.0; n];
+    for r in 0..rank.iter().map(|k| k + 1).max().unwrap_or(0) {
+        let front: Vec<usize> = (0..n).filter(|&i| rank[i] == r).collect();
+        for (k, _) in objs.iter().enumerate() {
+            let mut sorted = front.clone();
+            sorted.sort_by(|&i, &j| {
+                points[i][k]
+                    .value
+                    .partial_cmp(&points[j][k].value)
+                    .unwrap_or(Ordering::Equal)
+            });
+            let (first, last) = (sorted[0], sorted[sorted.len() - 1]);
+            let width = points[last][k].value - points[first][k].value;
+            dist[first] = f64::INFINITY;
+            dist[last] = f64::INFINITY;
+            if width > 0.0 {
+                for w in sorted.windows(3) {
+                    dist[w[1]] += (points[w[2]][k].value - points[w[0]][k].value) / width;
+                }
+            }
+        }
+    }
+    dist
+}
+
+/// Sort from the best, by rank and then by crowding distance
+pub fn sort(objs: &[Objective], items: &mut Vec<(Param, Vec<Metric>)>) {
+    let points: Vec<&[Metric]> = items.iter().map(|(_, ms)| ms.as_slice()).collect();
+    let rank = ranks(objs, &points);
+    let dist = crowding(objs, &points, &rank);
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    indices.sort_by(|&i, &j| {
+        rank[i]
+            .cmp(&rank[j])
+            .then(dist[j].partial_cmp(&dist[i]).unwrap_or(Ordering::Equal))
+    });
+    let mut sorted: Vec<_> = indices.iter().map(|&i| items[i].clone()).collect();
+    std::mem::swap(items, &mut sorted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(acc: f64, latency: f64) -> Vec<Metric> {
+        vec![
+            Metric {
+                metric: String::from("acc"),
+                value: acc,
+                step: None,
+            },
+            Metric {
+                metric: String::from("latency"),
+                value: latency,
+                step: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn non_dominated_sorting() {
+        let objs = [Objective::Maximize, Objective::Minimize];
+        let points = [
+            metrics(0.9, 10.0),
+            metrics(0.8, 5.0),
+            metrics(0.8, 10.0),
+            metrics(0.7, 20.0),
+        ];
+        let points: Vec<&[Metric]> = points.iter().map(|ms| ms.as_slice()).collect();
+        assert!(dominates(&objs, points[0], points[2]));
+        assert!(!dominates(&objs, points[0], points[1]));
+        assert_eq!(ranks(&objs, &points), vec![0, 0, 1, 2]);
+    }
+
+    #[test]
+    fn sort_single_objective() {
+        let objs = [Objective::Minimize];
+        let mut items: Vec<(Param, Vec<Metric>)> = [3.0, 1.0, 2.0]
+            .iter()
+            .map(|&v| (vec![], vec![metrics(v, 0.0)[0].clone()]))
+            .collect();
+        sort(&objs, &mut items);
+        let values: Vec<f64> = items.iter().map(|(_, ms)| ms[0].value).collect();
+        assert_eq!(values, vec![1.0, 2.0, 3.0]);
+    }
+}
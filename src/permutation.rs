@@ -0,0 +1,129 @@
+/// Lazy Permutations of 0..n (for the grid enumeration order)
+use std::str::FromStr;
+
+extern crate rand;
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Lexicographic,
+    Reverse,
+    Shuffle,
+}
+
+impl Order {
+    pub const NAMES: &'static [&'static str] = &["lexicographic", "reverse", "shuffle"];
+}
+
+impl FromStr for Order {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lexicographic" => Ok(Order::Lexicographic),
+            "reverse" => Ok(Order::Reverse),
+            "shuffle" => Ok(Order::Shuffle),
+            _ => Err(format!("Unknown order: {:?}", s)),
+        }
+    }
+}
+
+const ROUNDS: usize = 4;
+
+/// i-th element of a permutation of 0..n, computed in O(1) memory
+///
+/// Indices are u128, since a grid easily has more than 2^64 points.
+#[derive(Debug, Clone)]
+pub enum Permutation {
+    Identity,
+    Reverse(u128),
+    /// Feistel network on 2^(2 half) >= n with cycle-walking
+    Shuffle {
+        n: u128,
+        half: u32,
+        keys: [u64; ROUNDS],
+    },
+}
+
+/// splitmix64
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+impl Permutation {
+    pub fn new<R: Rng>(order: Order, n: u128, rng: &mut R) -> Self {
+        match order {
+            Order::Lexicographic => Permutation::Identity,
+            Order::Reverse => Permutation::Reverse(n),
+            Order::Shuffle => {
+                let bits = u128::BITS - n.saturating_sub(1).leading_zeros();
+                let mut keys = [0; ROUNDS];
+                for key in keys.iter_mut() {
+                    *key = rng.gen();
+                }
+                Permutation::Shuffle {
+                    n,
+                    half: bits.div_ceil(2),
+                    keys,
+                }
+            }
+        }
+    }
+
+    fn feistel(x: u128, half: u32, keys: &[u64; ROUNDS]) -> u128 {
+        let mask = (1u128 << half) - 1;
+        let (mut left, mut right) = (x >> half, x & mask);
+        for key in keys {
+            // half <= 64, so that right fits in u64
+            let next = left ^ (mix(right as u64 ^ key) as u128 & mask);
+            left = right;
+            right = next;
+        }
+        (left << half) | right
+    }
+
+    pub fn get(&self, i: u128) -> u128 {
+        match *self {
+            Permutation::Identity => i,
+            Permutation::Reverse(n) => n - 1 - i,
+            Permutation::Shuffle { n, half, ref keys } => {
+                let mut x = i;
+                loop {
+                    x = Permutation::feistel(x, half, keys);
+                    if x < n {
+                        return x;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutation_bijective() {
+        let mut rng = rand::thread_rng();
+        for n in [1, 2, 3, 10, 64, 100, 1000] {
+            for order in [Order::Lexicographic, Order::Reverse, Order::Shuffle] {
+                let perm = Permutation::new(order, n, &mut rng);
+                let mut xs: Vec<u128> = (0..n).map(|i| perm.get(i)).collect();
+                xs.sort_unstable();
+                assert_eq!(xs, (0..n).collect::<Vec<_>>());
+            }
+        }
+        // beyond u64
+        let n = 11u128.pow(30);
+        let perm = Permutation::new(Order::Shuffle, n, &mut rng);
+        let xs: Vec<u128> = (0..100).map(|i| perm.get(i)).collect();
+        assert!(xs.iter().all(|&x| x < n));
+        let mut distinct = xs.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), xs.len());
+    }
+}
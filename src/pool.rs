@@ -0,0 +1,108 @@
+/// Worker Pool of Trials (up to -j at once, refilled in the completion order)
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+/// Notifies the pool even when the job panics
+struct Done {
+    id: usize,
+    tx: Sender<usize>,
+}
+
+impl Drop for Done {
+    fn drop(&mut self) {
+        let _ = self.tx.send(self.id);
+    }
+}
+
+pub struct Pool {
+    size: usize,
+    running: HashMap<usize, JoinHandle<()>>,
+    tx: Sender<usize>,
+    rx: Receiver<usize>,
+}
+
+impl Pool {
+    pub fn new(size: usize) -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            size: size.max(1),
+            running: HashMap::new(),
+            tx,
+            rx,
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.running.len() >= self.size
+    }
+
+    pub fn spawn<F>(&mut self, id: usize, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let done = Done {
+            id,
+            tx: self.tx.clone(),
+        };
+        let handle = thread::spawn(move || {
+            let _done = done;
+            job();
+        });
+        self.running.insert(id, handle);
+    }
+
+    /// Wait for any job to finish (false if nothing is running)
+    pub fn wait_any(&mut self) -> bool {
+        if self.running.is_empty() {
+            return false;
+        }
+        let id = self.rx.recv().unwrap();
+        if let Some(handle) = self.running.remove(&id) {
+            if handle.join().is_err() {
+                eprintln!("\x1b[31m[Panicked] HID={}\x1b[0m", id);
+            }
+        }
+        true
+    }
+
+    pub fn wait_all(&mut self) {
+        while self.wait_any() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn pool_completion_order() {
+        let mut pool = Pool::new(2);
+        let slow_done = Arc::new(AtomicBool::new(false));
+        let flag = slow_done.clone();
+        pool.spawn(0, move || {
+            thread::sleep(Duration::from_millis(500));
+            flag.store(true, Ordering::SeqCst);
+        });
+        pool.spawn(1, || {});
+        assert!(pool.is_full());
+        assert!(pool.wait_any());
+        assert!(!pool.is_full());
+        assert!(!slow_done.load(Ordering::SeqCst));
+        pool.wait_all();
+        assert!(slow_done.load(Ordering::SeqCst));
+        assert!(!pool.wait_any());
+    }
+
+    #[test]
+    fn pool_survives_panic() {
+        let mut pool = Pool::new(2);
+        pool.spawn(0, || panic!("trial"));
+        pool.spawn(1, || {});
+        pool.wait_all();
+        assert!(!pool.wait_any());
+    }
+}
@@ -0,0 +1,90 @@
+/// Median Pruner
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::options::Objective;
+
+/// Stops a trial whose intermediate value is worse than the median
+/// of the other trials at the same step.
+pub struct MedianPruner {
+    objective: Objective,
+    metric: String,
+    startup: usize,
+    /// step -> (HID -> value)
+    history: Mutex<BTreeMap<u64, BTreeMap<usize, f64>>>,
+}
+
+impl MedianPruner {
+    pub fn new(objective: Objective, metric: &str, startup: usize) -> Self {
+        MedianPruner {
+            objective,
+            metric: metric.to_string(),
+            startup,
+            history: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn metric(&self) -> &String {
+        &self.metric
+    }
+
+    /// Record an intermediate value and tell whether the trial should be pruned
+    pub fn report(&self, hid: usize, step: u64, value: f64) -> bool {
+        let mut history = self.history.lock().unwrap();
+        let values = history.entry(step).or_default();
+        values.insert(hid, value);
+        let mut others: Vec<f64> = values
+            .iter()
+            .filter(|(&id, _)| id != hid)
+            .map(|(_, &v)| v)
+            .collect();
+        if others.is_empty() || others.len() < self.startup {
+            return false;
+        }
+        others.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = others.len();
+        let median = if n % 2 == 1 {
+            others[n / 2]
+        } else {
+            (others[n / 2 - 1] + others[n / 2]) / 2.0
+        };
+        match self.objective {
+            Objective::Maximize => value < median,
+            Objective::Minimize => value > median,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_pruner() {
+        // not before `startup` other trials reported at the step
+        let pruner = MedianPruner::new(Objective::Minimize, "loss", 2);
+        assert!(!pruner.report(0, 1, 1.0));
+        assert!(!pruner.report(1, 1, 5.0));
+        // odd: the median of [1, 5, 3] is 3
+        assert!(pruner.report(2, 1, 4.0));
+        assert!(!pruner.report(3, 1, 3.0));
+        // other steps are separate
+        assert!(!pruner.report(4, 2, 100.0));
+
+        // even: the median of [1, 0.5] is 0.75
+        let pruner = MedianPruner::new(Objective::Minimize, "loss", 0);
+        assert!(!pruner.report(0, 1, 1.0));
+        assert!(!pruner.report(1, 1, 0.5));
+        assert!(!pruner.report(2, 1, 0.7));
+        let pruner = MedianPruner::new(Objective::Minimize, "loss", 0);
+        pruner.report(0, 1, 1.0);
+        pruner.report(1, 1, 0.5);
+        assert!(pruner.report(2, 1, 0.8));
+
+        let pruner = MedianPruner::new(Objective::Maximize, "acc", 0);
+        pruner.report(0, 1, 0.2);
+        pruner.report(1, 1, 0.8);
+        assert!(pruner.report(2, 1, 0.4));
+        assert!(!pruner.report(3, 1, 0.6));
+    }
+}
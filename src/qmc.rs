@@ -0,0 +1,204 @@
+/// Quasi-Random Sampling on the Unit Cube (Sobol, Halton, Latin Hypercube)
+use std::str::FromStr;
+
+extern crate rand;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Init {
+    Random,
+    Sobol,
+    Halton,
+    Lhs,
+}
+
+impl Init {
+    pub const NAMES: &'static [&'static str] = &["random", "sobol", "halton", "lhs"];
+}
+
+impl FromStr for Init {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(Init::Random),
+            "sobol" => Ok(Init::Sobol),
+            "halton" => Ok(Init::Halton),
+            "lhs" => Ok(Init::Lhs),
+            _ => Err(format!("Unknown init: {:?}", s)),
+        }
+    }
+}
+
+/// Direction numbers (s, a, m_1..m_s) of the dimensions 2.. (Joe & Kuo)
+const SOBOL: &[(u32, u32, &[u32])] = &[
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+];
+
+const BITS: usize = 32;
+
+/// Direction vectors v_1..v_BITS of the d-th dimension
+fn directions(d: usize) -> Vec<u64> {
+    if d == 0 {
+        return (1..=BITS).map(|k| 1 << (BITS - k)).collect();
+    }
+    let (s, a, m) = SOBOL[d - 1];
+    let s = s as usize;
+    let mut v: Vec<u64> = vec![];
+    for k in 0..BITS {
+        let x = if k < s {
+            (m[k] as u64) << (BITS - 1 - k)
+        } else {
+            let mut x = v[k - s] ^ (v[k - s] >> s);
+            for j in 1..s {
+                if (a >> (s - 1 - j)) & 1 == 1 {
+                    x ^= v[k - j];
+                }
+            }
+            x
+        };
+        v.push(x);
+    }
+    v
+}
+
+fn sobol(dim: usize, n: usize) -> Vec<Vec<f64>> {
+    let dirs: Vec<Vec<u64>> = (0..dim).map(directions).collect();
+    (0..n)
+        .map(|i| {
+            dirs.iter()
+                .map(|v| {
+                    let x = (0..BITS)
+                        .filter(|k| (i >> k) & 1 == 1)
+                        .fold(0, |x, k| x ^ v[k]);
+                    x as f64 / (1u64 << BITS) as f64
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn primes(n: usize) -> Vec<usize> {
+    let mut ps = vec![];
+    let mut k = 2;
+    while ps.len() < n {
+        if ps.iter().all(|p| k % p != 0) {
+            ps.push(k);
+        }
+        k += 1;
+    }
+    ps
+}
+
+/// Van der Corput sequence in the base
+fn radical_inverse(mut i: usize, base: usize) -> f64 {
+    let mut x = 0.0;
+    let mut f = 1.0 / base as f64;
+    while i > 0 {
+        x += (i % base) as f64 * f;
+        i /= base;
+        f /= base as f64;
+    }
+    x
+}
+
+/// Halton sequence (skipping the origin)
+fn halton(dim: usize, n: usize) -> Vec<Vec<f64>> {
+    let bases = primes(dim);
+    (1..=n)
+        .map(|i| bases.iter().map(|&b| radical_inverse(i, b)).collect())
+        .collect()
+}
+
+/// Each dimension is split into n strata, and each stratum has exactly one point
+fn lhs<R: Rng>(dim: usize, n: usize, rng: &mut R) -> Vec<Vec<f64>> {
+    let columns: Vec<Vec<f64>> = (0..dim)
+        .map(|_| {
+            let mut strata: Vec<usize> = (0..n).collect();
+            strata.shuffle(rng);
+            strata
+                .into_iter()
+                .map(|k| (k as f64 + rng.gen::<f64>()) / n as f64)
+                .collect()
+        })
+        .collect();
+    (0..n)
+        .map(|i| columns.iter().map(|column| column[i]).collect())
+        .collect()
+}
+
+/// n points in [0, 1)^dim
+pub fn points<R: Rng>(
+    init: Init,
+    dim: usize,
+    n: usize,
+    rng: &mut R,
+) -> Result<Vec<Vec<f64>>, String> {
+    match init {
+        Init::Random => Ok((0..n)
+            .map(|_| (0..dim).map(|_| rng.gen::<f64>()).collect())
+            .collect()),
+        Init::Sobol if dim > SOBOL.len() + 1 => Err(format!(
+            "Sobol supports up to {} dimensions (got {})",
+            SOBOL.len() + 1,
+            dim
+        )),
+        Init::Sobol => Ok(sobol(dim, n)),
+        Init::Halton => Ok(halton(dim, n)),
+        Init::Lhs => Ok(lhs(dim, n, rng)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every stratum of every dimension has exactly one point
+    fn stratified(points: &[Vec<f64>], dim: usize) -> bool {
+        let n = points.len();
+        (0..dim).all(|d| {
+            let mut strata: Vec<usize> =
+                points.iter().map(|p| (p[d] * n as f64) as usize).collect();
+            strata.sort_unstable();
+            strata == (0..n).collect::<Vec<_>>()
+        })
+    }
+
+    #[test]
+    fn quasi_random_points() {
+        let mut rng = rand::thread_rng();
+        let dim = SOBOL.len() + 1;
+        assert!(stratified(
+            &points(Init::Sobol, dim, 16, &mut rng).unwrap(),
+            dim
+        ));
+        assert!(stratified(&points(Init::Lhs, 5, 7, &mut rng).unwrap(), 5));
+        assert!(stratified(
+            &points(Init::Halton, 1, 8, &mut rng).unwrap(),
+            1
+        ));
+        assert_eq!(
+            points(Init::Halton, 2, 2, &mut rng).unwrap(),
+            vec![vec![0.5, 1.0 / 3.0], vec![0.25, 2.0 / 3.0]]
+        );
+        assert!(points(Init::Sobol, dim + 1, 1, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+/// Random Search
+use std::collections::{HashSet, VecDeque};
+
+extern crate rand;
+use rand::rngs::StdRng;
+
+use crate::map::{Map, Param};
+use crate::metric::Metric;
+use crate::optimizer::{Best, Optimizer};
+use crate::options::Objective;
+use crate::qmc::{self, Init};
+
+/// Draws `trials` distinct points uniformly from the grid
+/// (and from the distributions for continuous keys)
+///
+/// With a quasi-random `init`, the points are drawn from the sequence first
+/// (duplicated or infeasible ones are replaced with uniform ones).
+pub struct RandomSearch {
+    map: Map,
+    trials: usize,
+    points: VecDeque<Param>,
+    rng: StdRng,
+    seen: HashSet<Vec<String>>,
+    best: Best,
+}
+
+impl RandomSearch {
+    pub fn new(
+        map: &Map,
+        objectives: &[Objective],
+        trials: usize,
+        init: Init,
+        mut rng: StdRng,
+    ) -> Result<Self, String> {
+        let trials = if map.is_continuous() {
+            trials
+        } else {
+            std::cmp::min(trials, map.len())
+        };
+        let points = match init {
+            Init::Random => VecDeque::new(),
+            _ => qmc::points(init, map.dims(), trials, &mut rng)?
+                .iter()
+                .map(|point| map.at(point))
+                .collect(),
+        };
+        Ok(RandomSearch {
+            map: map.clone(),
+            trials,
+            points,
+            rng,
+            seen: HashSet::new(),
+            best: Best::new(objectives),
+        })
+    }
+}
+
+impl Optimizer for RandomSearch {
+    fn propose(&mut self) -> Option<Param> {
+        if self.seen.len() >= self.trials {
+            return None;
+        }
+        while let Some(param) = self.points.pop_front() {
+            if self.map.feasible(&param) && self.seen.insert(self.map.args(&param)) {
+                return Some(param);
+            }
+        }
+        // give up when the grid seems exhausted (it may contain duplicated points)
+        for _ in 0..1000 {
+            let param = self.map.rand(&mut self.rng);
+            if self.seen.insert(self.map.args(&param)) {
+                return Some(param);
+            }
+        }
+        None
+    }
+
+    fn observe(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        self.best.update(param, result);
+    }
+
+    fn restore(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        self.seen.insert(self.map.args(&param));
+        self.observe(param, result);
+    }
+
+    fn best(&self) -> Vec<(Param, Vec<Metric>)> {
+        self.best.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Value;
+
+    fn run(map: &Map, trials: usize) -> Vec<Vec<String>> {
+        let rng = crate::util::seeded(0);
+        let mut random = RandomSearch::new(map, &[], trials, Init::Random, rng).unwrap();
+        let mut proposed = vec![];
+        while let Some(param) = random.propose() {
+            proposed.push(map.args(&param));
+            random.observe(param, None);
+        }
+        proposed
+    }
+
+    #[test]
+    fn random_search_trials() {
+        // 11^25 points overflow usize
+        let mut map = Map::new();
+        for k in 0..25 {
+            map.add(format!("K{}", k), Value::IntRange(0, 10, 1));
+        }
+        assert_eq!(map.len(), usize::MAX);
+        let proposed = run(&map, 100);
+        assert_eq!(proposed.len(), 100);
+        let distinct: HashSet<&Vec<String>> = proposed.iter().collect();
+        assert_eq!(distinct.len(), 100);
+        // the last keys vary as well as the first ones
+        let last: HashSet<&String> = proposed.iter().map(|args| &args[24]).collect();
+        assert!(last.len() > 5);
+
+        // capped by the grid size
+        let mut map = Map::new();
+        map.add(String::from("X"), Value::IntRange(0, 1, 1));
+        map.add(String::from("Y"), Value::IntRange(0, 2, 1));
+        let proposed = run(&map, 100);
+        assert_eq!(proposed.len(), 6);
+        let distinct: HashSet<&Vec<String>> = proposed.iter().collect();
+        assert_eq!(distinct.len(), 6);
+    }
+}
@@ -0,0 +1,266 @@
+/// Rebuild trials of an experiment from `.hake/log`
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+extern crate regex;
+use regex::Regex;
+
+use serde::Deserialize;
+
+use crate::map::{Map, Param};
+use crate::metric::{average_each, Metric};
+use crate::trial::Status;
+
+#[derive(Debug, Deserialize)]
+struct MakeArgs {
+    make_args: Vec<String>,
+    #[serde(default)]
+    git_hash: String,
+    #[serde(default)]
+    progress: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusLine {
+    status: String,
+}
+
+/// A trial recorded in a log file
+pub struct Record {
+    pub make_args: Vec<String>,
+    pub git_hash: String,
+    /// averaged metrics (None unless completed)
+    pub result: Option<Vec<Metric>>,
+    /// stopped by Ctrl-C (to run again)
+    pub interrupted: bool,
+    /// the progress of the optimizer when proposed
+    pub progress: Option<usize>,
+}
+
+/// (NAME, HID) from a log file name `{date}_{NAME}_{HID}`
+fn parse_file_name(file_name: &str) -> Option<(&str, usize)> {
+    let rest = file_name.get(9..)?;
+    let (name, id) = rest.rsplit_once('_')?;
+    Some((name, id.parse().ok()?))
+}
+
+/// The trial in a log file (None without the header)
+fn read(
+    path: &Path,
+    pattern: &Regex,
+    watching_metrics: &[String],
+) -> Result<Option<Record>, String> {
+    let file = File::open(path).map_err(|e| format!("Cannot open {:?}: {}", path, e))?;
+    Ok(parse(BufReader::new(file), pattern, watching_metrics))
+}
+
+/// The trial in the lines of a log
+fn parse<R: BufRead>(reader: R, pattern: &Regex, watching_metrics: &[String]) -> Option<Record> {
+    // a log has one block per sample (-M) or retry (--retry), each starting with make_args
+    let mut header = None;
+    let mut samples: Vec<Vec<Option<Metric>>> = vec![];
+    let mut completed = true;
+    // the last block failed (a failed block followed by another was retried)
+    let mut failed = false;
+    let mut interrupted = false;
+    for line in reader.lines().map_while(Result::ok) {
+        let message = match pattern.captures(&line).and_then(|c| c.get(2)) {
+            Some(message) => message.as_str().to_string(),
+            None => continue,
+        };
+        if let Ok(make) = serde_json::from_str::<MakeArgs>(&message) {
+            if failed {
+                samples.pop();
+                failed = false;
+            }
+            header = Some(make);
+            samples.push(vec![None; watching_metrics.len()]);
+        } else if let Ok(metric) = serde_json::from_str::<Metric>(&message) {
+            let k = watching_metrics.iter().position(|m| m == &metric.metric);
+            if let (Some(k), Some(last)) = (k, samples.last_mut()) {
+                last[k] = Some(metric);
+            }
+        } else if let Ok(line) = serde_json::from_str::<StatusLine>(&message) {
+            if line.status == Status::Failed.name() {
+                failed = true;
+            } else {
+                interrupted |= line.status == Status::Interrupted.name();
+                completed &= line.status == Status::Completed.name();
+            }
+        }
+    }
+
+    header.map(|make| {
+        let samples: Vec<Vec<Metric>> = samples
+            .into_iter()
+            .filter_map(|ms| ms.into_iter().collect())
+            .collect();
+        let result = if watching_metrics.is_empty() || !completed || failed {
+            None
+        } else {
+            average_each(samples)
+        };
+        Record {
+            make_args: make.make_args,
+            git_hash: make.git_hash,
+            result,
+            interrupted,
+            progress: make.progress,
+        }
+    })
+}
+
+/// Log files with (NAME, HID)
+fn logs() -> Result<Vec<(PathBuf, String, usize)>, String> {
+    let entries =
+        fs::read_dir(".hake/log/").map_err(|e| format!("Cannot read .hake/log: {}", e))?;
+    Ok(entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let (name, id) = parse_file_name(&file_name)?;
+            if path.is_file() {
+                Some((path, name.to_string(), id))
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// Completed trials of the experiment `name`, the next HID and the last progress of the optimizer
+#[allow(clippy::type_complexity)]
+pub fn load(
+    name: &str,
+    map: &Map,
+    watching_metrics: &[String],
+) -> Result<(Vec<(Param, Option<Vec<Metric>>)>, usize, Option<usize>), String> {
+    let pattern = Regex::new(r"^\[([^\]]*)\]\s+(.*)$").unwrap();
+    let mut trials = BTreeMap::new();
+    let mut next_id = 0;
+    let mut progress = None;
+
+    for (path, log_name, id) in logs()? {
+        if log_name != name {
+            continue;
+        }
+        next_id = std::cmp::max(next_id, id + 1);
+        let record = read(&path, &pattern, watching_metrics)?;
+        if record.as_ref().is_some_and(|record| record.interrupted) {
+            // stopped by Ctrl-C; to run again
+            continue;
+        }
+        progress = std::cmp::max(progress, record.as_ref().and_then(|record| record.progress));
+        match record.and_then(|record| Some((map.decode(&record.make_args)?, record.result))) {
+            Some(trial) => {
+                trials.insert(id, trial);
+            }
+            None => eprintln!(
+                "[Warning!] Cannot restore {:?} with the given mappings",
+                &path
+            ),
+        }
+    }
+
+    Ok((trials.into_values().collect(), next_id, progress))
+}
+
+/// All the trials in `.hake/log` (of any experiment)
+pub fn records(watching_metrics: &[String]) -> Result<Vec<Record>, String> {
+    let pattern = Regex::new(r"^\[([^\]]*)\]\s+(.*)$").unwrap();
+    if !Path::new(".hake/log/").exists() {
+        return Ok(vec![]);
+    }
+    let mut records = vec![];
+    for (path, _, _) in logs()? {
+        if let Some(record) = read(&path, &pattern, watching_metrics)? {
+            records.push(record);
+        }
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_log(log: &str) -> Record {
+        let pattern = Regex::new(r"^\[([^\]]*)\]\s+(.*)$").unwrap();
+        let metrics = vec![String::from("acc")];
+        parse(log.as_bytes(), &pattern, &metrics).unwrap()
+    }
+
+    fn acc(record: &Record) -> Option<f64> {
+        record.result.as_ref().map(|ms| ms[0].value)
+    }
+
+    #[test]
+    fn resume_parse_file_name() {
+        assert_eq!(
+            parse_file_name("20240101_happy_hake_00000012"),
+            Some(("happy_hake", 12))
+        );
+        assert_eq!(parse_file_name("20240101_x_y"), None);
+        assert_eq!(parse_file_name("short"), None);
+    }
+
+    #[test]
+    fn resume_read() {
+        let header = r#"{"name":"x","make_args":["train","X=1"],"git_hash":"abc"}"#;
+
+        // an old log without status lines; samples (-M) are averaged
+        let old = format!(
+            "[t] {}\n[t] {{\"metric\": \"acc\", \"value\": 1}}\n\
+             [t] {}\n[t] {{\"metric\": \"acc\", \"value\": 3}}\n[t] stuff\n",
+            header, header
+        );
+        let record = parse_log(&old);
+        assert_eq!(record.make_args, vec!["train", "X=1"]);
+        assert_eq!(record.git_hash, "abc");
+        assert_eq!(record.progress, None);
+        assert_eq!(acc(&record), Some(2.0));
+
+        let progress = r#"[t] {"name":"x","make_args":["train"],"git_hash":"abc","progress":3}"#;
+        assert_eq!(parse_log(progress).progress, Some(3));
+
+        // a failed block followed by its retry
+        let retried = format!(
+            "[t] {}\n[t] {{\"metric\": \"acc\", \"value\": 100}}\n\
+             [t] {{\"status\":\"failed\",\"exit_code\":2}}\n\
+             [t] {}\n[t] {{\"metric\": \"acc\", \"value\": 5}}\n\
+             [t] {{\"status\":\"completed\",\"exit_code\":0}}\n",
+            header, header
+        );
+        assert_eq!(acc(&parse_log(&retried)), Some(5.0));
+
+        // failed at last
+        let failed = format!(
+            "[t] {}\n[t] {{\"metric\": \"acc\", \"value\": 1}}\n\
+             [t] {{\"status\":\"failed\",\"exit_code\":2}}\n",
+            header
+        );
+        assert_eq!(acc(&parse_log(&failed)), None);
+
+        let interrupted = format!(
+            "[t] {}\n[t] {{\"metric\": \"acc\", \"value\": 1}}\n\
+             [t] {{\"status\":\"interrupted\",\"signal\":2}}\n",
+            header
+        );
+        let record = parse_log(&interrupted);
+        assert!(record.interrupted);
+        assert_eq!(acc(&record), None);
+
+        let pruned = format!(
+            "[t] {}\n[t] {{\"metric\": \"acc\", \"value\": 1}}\n\
+             [t] {{\"status\":\"pruned\",\"step\":1}}\n",
+            header
+        );
+        assert_eq!(acc(&parse_log(&pruned)), None);
+
+        let pattern = Regex::new(r"^\[([^\]]*)\]\s+(.*)$").unwrap();
+        assert!(parse("[t] stuff\n".as_bytes(), &pattern, &[]).is_none());
+    }
+}
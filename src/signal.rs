@@ -0,0 +1,74 @@
+/// SIGINT/SIGTERM/SIGHUP Handling (stop proposing, forward the signal to the running trials)
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+extern crate libc;
+
+/// the last signal received (0 for none)
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+/// how many times received
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+/// time for trials to exit after the forwarded signal
+static GRACE: OnceLock<Duration> = OnceLock::new();
+/// process groups of the running trials
+static GROUPS: Mutex<Vec<libc::pid_t>> = Mutex::new(Vec::new());
+
+extern "C" fn handle(signal: libc::c_int) {
+    SIGNAL.store(signal, Ordering::SeqCst);
+    COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+pub fn install(grace: Duration) {
+    let _ = GRACE.set(grace);
+    let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGHUP, handler);
+    }
+}
+
+/// The signal received, if any
+pub fn received() -> Option<i32> {
+    match SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+/// Received twice; no more grace
+pub fn forced() -> bool {
+    COUNT.load(Ordering::SeqCst) > 1
+}
+
+pub fn grace() -> Duration {
+    GRACE.get().copied().unwrap_or_default()
+}
+
+/// A trial started in its own process group
+pub fn enter(pid: u32) {
+    GROUPS.lock().unwrap().push(pid as libc::pid_t);
+}
+
+/// The trial is reaped
+pub fn leave(pid: u32) {
+    GROUPS
+        .lock()
+        .unwrap()
+        .retain(|&group| group != pid as libc::pid_t);
+}
+
+/// Kills the process groups still running when dropped
+pub struct Reaper;
+
+impl Drop for Reaper {
+    fn drop(&mut self) {
+        let groups = GROUPS.lock().unwrap_or_else(|e| e.into_inner());
+        for &group in groups.iter() {
+            unsafe {
+                libc::kill(-group, libc::SIGKILL);
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+/// Search Space File (--space space.json)
+///
+///   {
+///     "params": [
+///       {"name": "OPT", "values": ["sgd", "adam"]},
+///       {"name": "LR", "low": 1e-5, "high": 1e-1, "log": true},
+///       {"name": "MOMENTUM", "low": 0.5, "high": 0.9, "step": 0.1,
+///        "when": {"parent": "OPT", "values": ["sgd"]}},
+///       {"name": "EPOCHS", "value": "10..30..100"},
+///       {"name": "DROPOUT", "dist": "N(0.1,0.05,0,0.5)"},
+///       {"name": "BATCH", "default": 32}
+///     ],
+///     "constraints": ["EPOCHS > 10 || OPT == 'adam'"],
+///     "derived": [{"name": "WARMUP", "expr": "EPOCHS / 10"}]
+///   }
+///
+/// Keys are in the order of "params" (a parent should come before its children).
+/// Each key gives only one of value, dist, values, low/high or default (a fixed value).
+/// A range is int when both of low and high are integers, otherwise float.
+/// Constraints and derived keys are added after the mappings in the command line are merged.
+use std::fs;
+
+use serde::Deserialize;
+use serde_json::Value as Json;
+
+use crate::dist::Dist;
+use crate::map::{Condition, Map, Value};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Space {
+    params: Vec<KeySpec>,
+    #[serde(default)]
+    constraints: Vec<String>,
+    #[serde(default)]
+    derived: Vec<Derived>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Derived {
+    name: String,
+    expr: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeySpec {
+    name: String,
+    /// in the same syntax as the command line (e.g. "1..10")
+    value: Option<String>,
+    /// continuous distribution (e.g. "LogU(1e-5,1e-1)")
+    dist: Option<String>,
+    values: Option<Vec<Json>>,
+    low: Option<Json>,
+    high: Option<Json>,
+    /// skip for linear ranges, ratio for log ranges
+    step: Option<f64>,
+    #[serde(default)]
+    log: bool,
+    default: Option<Json>,
+    when: Option<When>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct When {
+    parent: String,
+    values: Vec<Json>,
+}
+
+fn show(x: &Json) -> String {
+    match x {
+        Json::String(s) => s.clone(),
+        x => x.to_string(),
+    }
+}
+
+fn number(name: &str, x: &Json) -> Result<f64, String> {
+    x.as_f64()
+        .ok_or(format!("`{}`: low/high should be numbers: {}", name, x))
+}
+
+impl KeySpec {
+    fn value(&self) -> Result<Value, String> {
+        let name = &self.name;
+        let given = [
+            self.value.is_some(),
+            self.dist.is_some(),
+            self.values.is_some(),
+            self.low.is_some() || self.high.is_some(),
+            self.default.is_some(),
+        ];
+        if given.iter().filter(|&&given| given).count() > 1 {
+            return Err(format!(
+                "`{}`: give only one of value, dist, values, low/high or default",
+                name
+            ));
+        }
+        if let Some(value) = &self.value {
+            return Value::from(value);
+        }
+        if let Some(dist) = &self.dist {
+            return Dist::parse(dist).map(Value::Dist);
+        }
+        if let Some(values) = &self.values {
+            return match values.len() {
+                0 => Err(format!("`{}`: values should not be empty", name)),
+                1 => Ok(Value::Val(show(&values[0]))),
+                _ => Ok(Value::Choice(values.iter().map(show).collect())),
+            };
+        }
+        match (&self.low, &self.high) {
+            (Some(low), Some(high)) => {
+                let is_int = low.is_i64()
+                    && high.is_i64()
+                    && self.step.is_none_or(|s| self.log || s.fract() == 0.0);
+                let (low, high) = (number(name, low)?, number(name, high)?);
+                if low > high {
+                    return Err(format!("`{}`: should be low <= high", name));
+                }
+                if self.log {
+                    if low <= 0.0 {
+                        return Err(format!("`{}`: log range should be positive", name));
+                    }
+                    let ratio = self.step.unwrap_or((high / low).powf(0.1));
+                    if ratio <= 1.0 && low < high {
+                        return Err(format!("`{}`: step (ratio) should be > 1", name));
+                    }
+                    Ok(if is_int {
+                        Value::IntLogRange(low as i64, high as i64, ratio)
+                    } else {
+                        Value::FloatLogRange(low, high, ratio)
+                    })
+                } else if is_int {
+                    let step = self.step.unwrap_or(1.0) as i64;
+                    if step <= 0 {
+                        return Err(format!("`{}`: step should be positive", name));
+                    }
+                    Ok(Value::IntRange(low as i64, high as i64, step))
+                } else {
+                    let step = self.step.unwrap_or((high - low) / 10.0);
+                    if step <= 0.0 && low < high {
+                        return Err(format!("`{}`: step should be positive", name));
+                    }
+                    Ok(Value::FloatRange(low, high, step))
+                }
+            }
+            (None, None) => match &self.default {
+                Some(x) => Ok(Value::Val(show(x))),
+                None => Err(format!(
+                    "`{}`: needs one of value, values, low/high or default",
+                    name
+                )),
+            },
+            _ => Err(format!("`{}`: needs both of low and high", name)),
+        }
+    }
+}
+
+/// The keys, and the constraints and derived keys (KEY, EXPR) over them
+pub struct SearchSpace {
+    pub map: Map,
+    pub constraints: Vec<String>,
+    pub derived: Vec<(String, String)>,
+}
+
+/// SearchSpace from a JSON text
+pub fn parse(text: &str) -> Result<SearchSpace, String> {
+    let space: Space =
+        serde_json::from_str(text).map_err(|e| format!("Invalid Space File: {}", e))?;
+    let mut map = Map::new();
+    for spec in space.params.iter() {
+        map.add(spec.name.clone(), spec.value()?);
+        if let Some(when) = &spec.when {
+            let cond = Condition {
+                parent: when.parent.clone(),
+                values: when.values.iter().map(show).collect(),
+            };
+            map.add_condition(&spec.name, cond)?;
+        }
+    }
+    Ok(SearchSpace {
+        map,
+        constraints: space.constraints,
+        derived: space
+            .derived
+            .into_iter()
+            .map(|derived| (derived.name, derived.expr))
+            .collect(),
+    })
+}
+
+pub fn load(path: &str) -> Result<SearchSpace, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    parse(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn space_parse() {
+        let space = parse(
+            r#"{
+              "params": [
+                {"name": "OPT", "values": ["sgd", "adam"]},
+                {"name": "LR", "low": 0.001, "high": 0.1, "log": true, "step": 10},
+                {"name": "MOMENTUM", "low": 0, "high": 9, "step": 3,
+                 "when": {"parent": "OPT", "values": ["sgd"]}},
+                {"name": "EPOCHS", "value": "1..3"},
+                {"name": "BATCH", "default": 32},
+                {"name": "W", "dist": "N(0,1)"}
+              ],
+              "constraints": ["EPOCHS > 1"],
+              "derived": [{"name": "HALF", "expr": "EPOCHS / 2"}]
+            }"#,
+        )
+        .unwrap();
+        let map = &space.map;
+        let keys: Vec<&str> = map.data.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, vec!["OPT", "LR", "MOMENTUM", "EPOCHS", "BATCH", "W"]);
+        assert_eq!(map.data[1].1, Value::FloatLogRange(0.001, 0.1, 10.0));
+        assert_eq!(map.data[2].1, Value::IntRange(0, 9, 3));
+        assert_eq!(map.data[3].1, Value::IntRange(1, 3, 1));
+        assert_eq!(map.data[4].1, Value::Val(String::from("32")));
+        assert!(map.data[5].1.is_continuous());
+        assert_eq!(map.conditions.len(), 1);
+        assert_eq!(space.constraints, vec!["EPOCHS > 1"]);
+        assert_eq!(
+            space.derived,
+            vec![(String::from("HALF"), String::from("EPOCHS / 2"))]
+        );
+
+        assert!(parse(r#"{"params": [{"name": "X", "low": 1}]}"#).is_err());
+        assert!(parse(r#"{"params": [{"name": "X", "lo": 1, "high": 2}]}"#).is_err());
+        assert!(parse(r#"{"params": [{"name": "X", "low": 2, "high": 1}]}"#).is_err());
+        assert!(parse(r#"{"params": [{"name": "X", "value": "1..3", "default": 2}]}"#).is_err());
+        assert!(
+            parse(r#"{"params": [{"name": "X", "low": 1, "high": 3, "default": 2}]}"#).is_err()
+        );
+        assert!(parse(r#"{"params": [{"name": "X", "values": [1], "dist": "U(0,1)"}]}"#).is_err());
+    }
+}
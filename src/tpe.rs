@@ -0,0 +1,315 @@
+/// Tree-structured Parzen Estimator
+extern crate rand;
+use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::map::{Map, Param, Value};
+use crate::metric::Metric;
+use crate::optimizer::{Best, Optimizer};
+use crate::options::Objective;
+use crate::pareto;
+use crate::util::gauss;
+
+/// Ratio of the good group
+const GAMMA: f64 = 0.25;
+/// Num of candidates drawn from the good density per proposal
+const NUM_CANDIDATES: usize = 24;
+
+/// Proposes each point from all the completed trials so far,
+/// so it never waits for running trials.
+pub struct Tpe {
+    map: Map,
+    objectives: Vec<Objective>,
+    trials: usize,
+    startup: usize,
+    proposed: usize,
+    history: Vec<(Param, Vec<Metric>)>,
+    rng: StdRng,
+    best: Best,
+}
+
+impl Tpe {
+    pub fn new(
+        map: &Map,
+        objectives: &[Objective],
+        trials: usize,
+        startup: usize,
+        rng: StdRng,
+    ) -> Self {
+        Tpe {
+            map: map.clone(),
+            objectives: objectives.to_vec(),
+            trials,
+            startup,
+            proposed: 0,
+            history: vec![],
+            rng,
+            best: Best::new(objectives),
+        }
+    }
+
+    /// Split history into (good, bad)
+    fn split(&self) -> (Vec<Param>, Vec<Param>) {
+        let mut sorted = self.history.clone();
+        pareto::sort(&self.objectives, &mut sorted);
+        let n = ((sorted.len() as f64) * GAMMA).ceil() as usize;
+        let mut good: Vec<Param> = sorted.into_iter().map(|(param, _)| param).collect();
+        let bad = good.split_off(n);
+        (good, bad)
+    }
+
+    fn suggest(&mut self) -> Param {
+        let (good, bad) = self.split();
+        let (map, rng) = (&self.map, &mut self.rng);
+
+        use Value::*;
+
+        let param = map
+            .data
+            .iter()
+            .enumerate()
+            .map(|(i, (key, val))| {
+                // only the trials where the key is active
+                let active = |ps: &[Param]| -> Vec<Param> {
+                    ps.iter().filter(|p| map.active(p, i)).cloned().collect()
+                };
+                let (good, bad) = (active(&good), active(&bad));
+                let value = match val {
+                    Val(_) | Int(_) | Float(_) => val.clone(),
+                    IntRange(begin, end, _) => {
+                        let (low, high) = (*begin.min(end), *begin.max(end));
+                        let x = numeric(&good, &bad, i, low as f64, high as f64, false, rng);
+                        Int((x.round() as i64).clamp(low, high))
+                    }
+                    FloatRange(begin, end, _) => Float(numeric(
+                        &good,
+                        &bad,
+                        i,
+                        begin.min(*end),
+                        begin.max(*end),
+                        false,
+                        rng,
+                    )),
+                    IntLogRange(begin, end, _) => {
+                        let (low, high) = (*begin.min(end), *begin.max(end));
+                        let x = numeric(&good, &bad, i, low as f64, high as f64, true, rng);
+                        Int((x.round() as i64).clamp(low, high))
+                    }
+                    FloatLogRange(begin, end, _) => Float(numeric(
+                        &good,
+                        &bad,
+                        i,
+                        begin.min(*end),
+                        begin.max(*end),
+                        true,
+                        rng,
+                    )),
+                    Dist(dist) => {
+                        let (low, high) = dist.range();
+                        let x = numeric(&good, &bad, i, low, high, dist.is_log(), rng);
+                        Float(dist.clip(x))
+                    }
+                    Choice(choices) => {
+                        let counts = |ps: &[Param]| -> Vec<f64> {
+                            choices
+                                .iter()
+                                .map(|c| {
+                                    1.0 + ps.iter().filter(|p| p[i].1 == Val(c.clone())).count()
+                                        as f64
+                                })
+                                .collect()
+                        };
+                        let l = counts(&good);
+                        let g = counts(&bad);
+                        let lsum: f64 = l.iter().sum();
+                        let gsum: f64 = g.iter().sum();
+                        let mut best = (0, f64::MIN);
+                        for _ in 0..NUM_CANDIDATES {
+                            let k = categorical(&l, rng);
+                            let score = (l[k] / lsum) / (g[k] / gsum);
+                            if score > best.1 {
+                                best = (k, score);
+                            }
+                        }
+                        Val(choices[best.0].clone())
+                    }
+                };
+                (key.clone(), value)
+            })
+            .collect();
+        map.normalize(param)
+    }
+}
+
+/// Parzen estimation for the i-th key on [low, high] (or on its log scale)
+fn numeric<R: Rng>(
+    good: &[Param],
+    bad: &[Param],
+    i: usize,
+    low: f64,
+    high: f64,
+    log: bool,
+    rng: &mut R,
+) -> f64 {
+    let scale = |x: f64| if log { x.ln() } else { x };
+    let xs = |ps: &[Param]| -> Vec<f64> {
+        ps.iter()
+            .filter_map(|p| match p[i].1 {
+                Value::Int(x) => Some(scale(x as f64)),
+                Value::Float(x) => Some(scale(x)),
+                _ => None,
+            })
+            .collect()
+    };
+    let l = Parzen::new(xs(good), scale(low), scale(high));
+    let g = Parzen::new(xs(bad), scale(low), scale(high));
+    let x = l.best_of(&g, rng);
+    if log {
+        x.exp().clamp(low, high)
+    } else {
+        x
+    }
+}
+
+/// Draw an index with the (unnormalized) weights
+fn categorical<R: Rng>(weights: &[f64], rng: &mut R) -> usize {
+    let total: f64 = weights.iter().sum();
+    let mut r = Uniform::new(0.0, total).sample(rng);
+    for (k, w) in weights.iter().enumerate() {
+        if r < *w {
+            return k;
+        }
+        r -= w;
+    }
+    weights.len() - 1
+}
+
+/// Gaussian kernel density on [low, high] mixed with the uniform prior
+struct Parzen {
+    mus: Vec<f64>,
+    sigma: f64,
+    low: f64,
+    high: f64,
+}
+
+impl Parzen {
+    fn new(mus: Vec<f64>, low: f64, high: f64) -> Self {
+        let n = mus.len() as f64 + 1.0;
+        let sigma = f64::max((high - low) * n.powf(-0.2), 1e-12);
+        Parzen {
+            mus,
+            sigma,
+            low,
+            high,
+        }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        let k = Uniform::from(0..self.mus.len() + 1).sample(rng);
+        if k == self.mus.len() || self.low >= self.high {
+            Uniform::new_inclusive(self.low, self.high).sample(rng)
+        } else {
+            (self.mus[k] + self.sigma * gauss(rng)).clamp(self.low, self.high)
+        }
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        let prior = if self.high > self.low {
+            1.0 / (self.high - self.low)
+        } else {
+            1.0
+        };
+        let kernels: f64 = self
+            .mus
+            .iter()
+            .map(|mu| {
+                let z = (x - mu) / self.sigma;
+                (-0.5 * z * z).exp() / (self.sigma * (2.0 * std::f64::consts::PI).sqrt())
+            })
+            .sum();
+        (prior + kernels) / (self.mus.len() as f64 + 1.0)
+    }
+
+    /// The candidate maximizing l(x)/g(x), where self is l
+    fn best_of<R: Rng>(&self, g: &Parzen, rng: &mut R) -> f64 {
+        let mut best = (self.low, f64::MIN);
+        for _ in 0..NUM_CANDIDATES {
+            let x = self.sample(rng);
+            let score = self.pdf(x) / g.pdf(x);
+            if score > best.1 {
+                best = (x, score);
+            }
+        }
+        best.0
+    }
+}
+
+impl Optimizer for Tpe {
+    fn propose(&mut self) -> Option<Param> {
+        if self.proposed >= self.trials {
+            return None;
+        }
+        self.proposed += 1;
+        if self.history.len() < self.startup {
+            return Some(self.map.rand(&mut self.rng));
+        }
+        for _ in 0..100 {
+            let param = self.suggest();
+            if self.map.feasible(&param) {
+                return Some(param);
+            }
+        }
+        Some(self.map.rand(&mut self.rng))
+    }
+
+    fn observe(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        if let Some(metrics) = result {
+            self.history.push((param.clone(), metrics.clone()));
+            self.best.update(param, Some(metrics));
+        }
+    }
+
+    fn restore(&mut self, param: Param, result: Option<Vec<Metric>>) {
+        self.proposed += 1;
+        self.observe(param, result);
+    }
+
+    fn best(&self) -> Vec<(Param, Vec<Metric>)> {
+        self.best.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tpe_descending_range() {
+        let mut map = Map::new();
+        map.add(
+            String::from("X"),
+            Value::from(&String::from("10..9..0")).unwrap(),
+        );
+        map.add(
+            String::from("Y"),
+            Value::from(&String::from("0...-1...-5")).unwrap(),
+        );
+        let rng = crate::util::seeded(0);
+        let mut tpe = Tpe::new(&map, &[Objective::Minimize], 20, 1, rng);
+        while let Some(param) = tpe.propose() {
+            let (x, y) = match (&param[0].1, &param[1].1) {
+                (Value::Int(x), Value::Float(y)) => (*x, *y),
+                _ => panic!(),
+            };
+            assert!((0..=10).contains(&x));
+            assert!((-5.0..=0.0).contains(&y));
+            let loss = Metric {
+                metric: String::from("loss"),
+                value: x as f64 - y,
+                step: None,
+            };
+            tpe.observe(param, Some(vec![loss]));
+        }
+    }
+}
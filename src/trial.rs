@@ -0,0 +1,24 @@
+/// How a trial ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Completed,
+    Pruned,
+    /// make exited with non-zero (or by a signal)
+    Failed,
+    /// killed by --trial-timeout
+    Timeout,
+    /// stopped by SIGINT/SIGTERM/SIGHUP
+    Interrupted,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Completed => "completed",
+            Status::Pruned => "pruned",
+            Status::Failed => "failed",
+            Status::Timeout => "timeout",
+            Status::Interrupted => "interrupted",
+        }
+    }
+}
@@ -1,33 +1,39 @@
 extern crate rand;
 use rand::distributions::{Distribution, Uniform};
+use rand::Rng;
 use std::collections::BTreeSet;
 
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
-pub struct Total<T>(pub T);
-impl<T: PartialEq> Eq for Total<T> {}
-impl<T: PartialOrd> Ord for Total<T> {
-    fn cmp(&self, rhs: &Total<T>) -> std::cmp::Ordering {
-        self.0.partial_cmp(&rhs.0).unwrap()
-    }
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// RNG seeded by --seed
+pub fn seeded(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
 }
 
-fn choose<T>(xs: &Vec<T>, except: &BTreeSet<usize>) -> usize {
-    let mut rng = rand::thread_rng();
+fn choose<T, R: Rng>(xs: &[T], except: &BTreeSet<usize>, rng: &mut R) -> usize {
     let indices = Uniform::from(0..xs.len());
-    let mut idx = indices.sample(&mut rng);
+    let mut idx = indices.sample(rng);
     while except.contains(&idx) {
-        idx = indices.sample(&mut rng);
+        idx = indices.sample(rng);
     }
     idx
 }
 
-pub fn sample<T>(xs: &Vec<T>, n: usize) -> Vec<usize> {
+pub fn sample<T, R: Rng>(xs: &[T], n: usize, rng: &mut R) -> Vec<usize> {
     let mut r = vec![];
     let mut except = BTreeSet::new();
     while r.len() < n {
-        let i = choose(&xs, &except);
+        let i = choose(xs, &except, rng);
         r.push(i);
         except.insert(i);
     }
     r
 }
+
+/// Standard normal by Box-Muller
+pub fn gauss<R: Rng>(rng: &mut R) -> f64 {
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen::<f64>();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}